@@ -0,0 +1,47 @@
+//! Indexes an osu! Songs folder and prints a summary.
+//!
+//! Usage: cargo run --release --features index --example index_songs -- <Songs folder>
+
+use std::time::Instant;
+
+use osu_parser::{index_songs, IndexOptions};
+
+fn main() {
+    let root = match std::env::args().nth(1) {
+        Some(root) => root,
+        None => {
+            eprintln!("Usage: index_songs <Songs folder>");
+            std::process::exit(1);
+        }
+    };
+
+    let start = Instant::now();
+    let index = index_songs(
+        &root,
+        IndexOptions {
+            count_hit_objects: true,
+            lenient: true,
+        },
+    );
+    let elapsed = start.elapsed();
+
+    for error in &index.errors {
+        eprintln!("{}: {}", error.path.display(), error.error);
+    }
+
+    let objects: u64 = index
+        .sets
+        .iter()
+        .flat_map(|set| &set.beatmaps)
+        .filter_map(|beatmap| beatmap.header.hit_objects)
+        .map(|summary| u64::from(summary.count()))
+        .sum();
+    println!(
+        "{} beatmap sets, {} beatmaps, {} hit objects, {} errors in {:.2?}",
+        index.sets.len(),
+        index.beatmap_count(),
+        objects,
+        index.errors.len(),
+        elapsed
+    );
+}
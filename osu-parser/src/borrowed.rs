@@ -0,0 +1,316 @@
+use std::{borrow::Cow, ops::Range};
+
+use osu_types::{
+    storyboard::{
+        Animation, Command, Layer, LoopType, Origin, Sample, Sprite, Storyboard, StoryboardObject,
+    },
+    BeatmapDifficulty, BeatmapEditor, BeatmapGeneralData, BeatmapInfo, BeatmapMetadata, ComboColor,
+    Countdown, Event, HitObject, HitSample, HitSound, Mode, OsuPoint, OverlayPosition, SampleSet,
+    SpecificHitObject, Time, TimingPoint, RGB,
+};
+
+use crate::Beatmap;
+
+/// A [`Beatmap`] that borrows its strings from the parsed content instead of copying them: the
+/// `[General]` and `[Metadata]` strings and the file names of events, storyboard objects and hit
+/// samples. Storyboard lines that use variables are expanded into new strings, so the file names
+/// on them are owned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatmapRef<'a> {
+    pub format_version: u8,
+    pub general: BeatmapGeneralDataRef<'a>,
+    pub editor: BeatmapEditor,
+    pub metadata: BeatmapMetadataRef<'a>,
+    pub difficulty: BeatmapDifficulty,
+    pub events: Vec<EventRef<'a>>,
+    pub storyboard: StoryboardRef<'a>,
+    pub colors: Vec<ComboColor>,
+    pub slider_track_override: Option<RGB<u8>>,
+    pub slider_border: Option<RGB<u8>>,
+    pub timing_points: Vec<TimingPoint>,
+    pub hit_objects: Vec<HitObjectRef<'a>>,
+}
+
+/// Borrowed version of [`BeatmapGeneralData`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatmapGeneralDataRef<'a> {
+    pub audio_file_name: Cow<'a, str>,
+    pub audio_lead_in: Time,
+    pub preview_time: Option<Time>,
+    pub countdown: Option<Countdown>,
+    pub sample_set: SampleSet,
+    pub stack_leniency: f32,
+    pub mode: Mode,
+    pub letterbox_in_breaks: bool,
+    pub story_fire_in_front: bool,
+    pub use_skin_sprites: bool,
+    pub always_show_playfield: bool,
+    pub overlay_position: OverlayPosition,
+    pub skin_preference: Option<Cow<'a, str>>,
+    pub epilepsy_warning: bool,
+    pub countdown_offset: u32,
+    pub special_style: bool,
+    pub widescreen_storyboard: bool,
+    pub samples_match_playback_rate: bool,
+    pub audio_hash: Option<Cow<'a, str>>,
+}
+
+/// Borrowed version of [`BeatmapMetadata`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatmapMetadataRef<'a> {
+    pub title: Cow<'a, str>,
+    pub title_unicode: Cow<'a, str>,
+    pub artist: Cow<'a, str>,
+    pub artist_unicode: Cow<'a, str>,
+    pub creator: Cow<'a, str>,
+    pub version: Cow<'a, str>,
+    pub source: Cow<'a, str>,
+    pub tags: Cow<'a, str>,
+    pub beatmap_id: i32,
+    pub beatmap_set_id: i32,
+}
+
+/// Borrowed version of [`Event`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventRef<'a> {
+    Background {
+        filename: Cow<'a, str>,
+        offset: OsuPoint,
+    },
+    Video {
+        start_time: Time,
+        filename: Cow<'a, str>,
+        offset: OsuPoint,
+    },
+    Break(Range<Time>),
+    Color {
+        start_time: Time,
+        color: RGB<u8>,
+    },
+}
+
+/// Borrowed version of [`Storyboard`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StoryboardRef<'a> {
+    pub objects: Vec<StoryboardObjectRef<'a>>,
+}
+
+/// Borrowed version of [`StoryboardObject`]. Commands are owned, they don't have file names.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoryboardObjectRef<'a> {
+    Sprite(SpriteRef<'a>),
+    Animation(AnimationRef<'a>),
+    Sample(SampleRef<'a>),
+}
+
+/// Borrowed version of [`Sprite`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteRef<'a> {
+    pub layer: Layer,
+    pub origin: Origin,
+    pub filename: Cow<'a, str>,
+    pub position: mint::Point2<f32>,
+    pub commands: Vec<Command>,
+}
+
+/// Borrowed version of [`Animation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationRef<'a> {
+    pub layer: Layer,
+    pub origin: Origin,
+    pub filename: Cow<'a, str>,
+    pub position: mint::Point2<f32>,
+    pub frame_count: u32,
+    pub frame_delay: f32,
+    pub loop_type: LoopType,
+    pub commands: Vec<Command>,
+}
+
+/// Borrowed version of [`Sample`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRef<'a> {
+    pub time: Time,
+    pub layer: Layer,
+    pub filename: Cow<'a, str>,
+    pub volume: u8,
+}
+
+/// Borrowed version of [`HitObject`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitObjectRef<'a> {
+    pub position: OsuPoint,
+    pub time: Time,
+    pub specific: SpecificHitObject,
+    pub hit_sound: HitSound,
+    pub hit_sample: HitSampleRef<'a>,
+    pub new_combo: bool,
+    pub combo_skip: u8,
+}
+
+/// Borrowed version of [`HitSample`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitSampleRef<'a> {
+    pub normal_set: Option<SampleSet>,
+    pub addition_set: Option<SampleSet>,
+    pub index: u8,
+    pub volume: u8,
+    pub filename: Option<Cow<'a, str>>,
+}
+
+impl BeatmapRef<'_> {
+    pub fn into_owned(self) -> Beatmap {
+        Beatmap {
+            format_version: self.format_version,
+            info: BeatmapInfo {
+                general_data: self.general.into_owned(),
+                metadata: self.metadata.into_owned(),
+                difficulty: self.difficulty,
+            },
+            editor: self.editor,
+            events: self.events.into_iter().map(EventRef::into_owned).collect(),
+            storyboard: self.storyboard.into_owned(),
+            colors: self.colors,
+            slider_track_override: self.slider_track_override,
+            slider_border: self.slider_border,
+            timing_points: self.timing_points,
+            hit_objects: self
+                .hit_objects
+                .into_iter()
+                .map(HitObjectRef::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl BeatmapGeneralDataRef<'_> {
+    pub fn into_owned(self) -> BeatmapGeneralData {
+        BeatmapGeneralData {
+            audio_file_name: self.audio_file_name.into_owned(),
+            audio_lead_in: self.audio_lead_in,
+            preview_time: self.preview_time,
+            countdown: self.countdown,
+            sample_set: self.sample_set,
+            stack_leniency: self.stack_leniency,
+            mode: self.mode,
+            letterbox_in_breaks: self.letterbox_in_breaks,
+            story_fire_in_front: self.story_fire_in_front,
+            use_skin_sprites: self.use_skin_sprites,
+            always_show_playfield: self.always_show_playfield,
+            overlay_position: self.overlay_position,
+            skin_preference: self.skin_preference.map(Cow::into_owned),
+            epilepsy_warning: self.epilepsy_warning,
+            countdown_offset: self.countdown_offset,
+            special_style: self.special_style,
+            widescreen_storyboard: self.widescreen_storyboard,
+            samples_match_playback_rate: self.samples_match_playback_rate,
+            audio_hash: self.audio_hash.map(Cow::into_owned),
+        }
+    }
+}
+
+impl BeatmapMetadataRef<'_> {
+    pub fn into_owned(self) -> BeatmapMetadata {
+        BeatmapMetadata {
+            title: self.title.into_owned(),
+            title_unicode: self.title_unicode.into_owned(),
+            artist: self.artist.into_owned(),
+            artist_unicode: self.artist_unicode.into_owned(),
+            creator: self.creator.into_owned(),
+            version: self.version.into_owned(),
+            source: self.source.into_owned(),
+            tags: self.tags.into_owned(),
+            beatmap_id: self.beatmap_id,
+            beatmap_set_id: self.beatmap_set_id,
+        }
+    }
+}
+
+impl EventRef<'_> {
+    pub fn into_owned(self) -> Event {
+        match self {
+            EventRef::Background { filename, offset } => Event::Background {
+                filename: filename.into_owned(),
+                offset,
+            },
+            EventRef::Video {
+                start_time,
+                filename,
+                offset,
+            } => Event::Video {
+                start_time,
+                filename: filename.into_owned(),
+                offset,
+            },
+            EventRef::Break(range) => Event::Break(range),
+            EventRef::Color { start_time, color } => Event::Color { start_time, color },
+        }
+    }
+}
+
+impl StoryboardRef<'_> {
+    pub fn into_owned(self) -> Storyboard {
+        Storyboard {
+            objects: self
+                .objects
+                .into_iter()
+                .map(StoryboardObjectRef::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl StoryboardObjectRef<'_> {
+    pub fn into_owned(self) -> StoryboardObject {
+        match self {
+            StoryboardObjectRef::Sprite(sprite) => StoryboardObject::Sprite(Sprite {
+                layer: sprite.layer,
+                origin: sprite.origin,
+                filename: sprite.filename.into_owned(),
+                position: sprite.position,
+                commands: sprite.commands,
+            }),
+            StoryboardObjectRef::Animation(animation) => StoryboardObject::Animation(Animation {
+                layer: animation.layer,
+                origin: animation.origin,
+                filename: animation.filename.into_owned(),
+                position: animation.position,
+                frame_count: animation.frame_count,
+                frame_delay: animation.frame_delay,
+                loop_type: animation.loop_type,
+                commands: animation.commands,
+            }),
+            StoryboardObjectRef::Sample(sample) => StoryboardObject::Sample(Sample {
+                time: sample.time,
+                layer: sample.layer,
+                filename: sample.filename.into_owned(),
+                volume: sample.volume,
+            }),
+        }
+    }
+}
+
+impl HitObjectRef<'_> {
+    pub fn into_owned(self) -> HitObject {
+        HitObject {
+            position: self.position,
+            time: self.time,
+            specific: self.specific,
+            hit_sound: self.hit_sound,
+            hit_sample: self.hit_sample.into_owned(),
+            new_combo: self.new_combo,
+            combo_skip: self.combo_skip,
+        }
+    }
+}
+
+impl HitSampleRef<'_> {
+    pub fn into_owned(self) -> HitSample {
+        HitSample {
+            normal_set: self.normal_set,
+            addition_set: self.addition_set,
+            index: self.index,
+            volume: self.volume,
+            filename: self.filename.map(Cow::into_owned),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{
+    error::{OsuParserError, OsuParserResult},
+    load_content, Beatmap, BeatmapParseOptions,
+};
+
+/// MD5 of the raw bytes of a .osu file, which the client uses to identify difficulties in
+/// replays, scores.db and osu!.db.
+pub fn checksum(bytes: &[u8]) -> [u8; 16] {
+    md5::compute(bytes).0
+}
+
+/// The checksum as lowercase hex, like it is written in the client's databases.
+pub fn checksum_hex(checksum: &[u8; 16]) -> String {
+    format!("{:x}", md5::Digest(*checksum))
+}
+
+/// Parses the raw bytes of a .osu file and returns the beatmap with its checksum.
+pub fn load_bytes(
+    bytes: &[u8],
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(Beatmap, [u8; 16])> {
+    let content = std::str::from_utf8(bytes).map_err(|e| {
+        OsuParserError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    })?;
+    let beatmap = load_content(content, options)?;
+    Ok((beatmap, checksum(bytes)))
+}
+
+/// Like [`load_bytes`], but reads the bytes from a file.
+pub fn load_file_with_checksum(
+    path: impl AsRef<std::path::Path>,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(Beatmap, [u8; 16])> {
+    let bytes = std::fs::read(path).map_err(OsuParserError::from)?;
+    load_bytes(&bytes, options)
+}
@@ -0,0 +1,72 @@
+use osu_types::{ComboColor, SpecificHitObject};
+
+use crate::Beatmap;
+
+/// Where an object is in the combos of a beatmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComboInfo {
+    /// Number of the combo the object is in, counting from 1.
+    pub combo_number: u32,
+    /// Index of the object in its combo, counting from 0. The number shown on a circle is one
+    /// higher.
+    pub index_in_combo: u32,
+    /// Index into [`Beatmap::combo_colors`].
+    pub color_index: usize,
+    pub color: ComboColor,
+}
+
+impl Beatmap {
+    /// Combo information for every hit object, in the same order as [`Beatmap::hit_objects`].
+    ///
+    /// Like in the client, the first object always starts a combo, and spinners never start one
+    /// themselves. Instead the object after a spinner starts a new combo, which also skips the
+    /// colours the spinner would have skipped. In file format v8 and older, the object after a
+    /// spinner always starts a new combo.
+    pub fn combos(&self) -> Vec<ComboInfo> {
+        let colors = self.combo_colors();
+        let mut combos: Vec<ComboInfo> = Vec::with_capacity(self.hit_objects.len());
+        let mut force_new_combo = false;
+        let mut extra_skip = 0;
+
+        for object in &self.hit_objects {
+            let previous = combos.last().copied();
+            let is_spinner = matches!(object.specific, SpecificHitObject::Spinner { .. });
+
+            let mut new_combo = object.new_combo;
+            let mut skip = object.combo_skip as usize;
+            if is_spinner {
+                force_new_combo |= new_combo || self.format_version <= 8;
+                extra_skip += skip;
+                new_combo = false;
+                skip = 0;
+            } else {
+                new_combo |= force_new_combo;
+                skip += extra_skip;
+                force_new_combo = false;
+                extra_skip = 0;
+            }
+
+            let (combo_number, index_in_combo, color_index) = match previous {
+                Some(previous) if !new_combo => (
+                    previous.combo_number,
+                    previous.index_in_combo + 1,
+                    previous.color_index,
+                ),
+                Some(previous) => (
+                    previous.combo_number + 1,
+                    0,
+                    previous.color_index + 1 + skip,
+                ),
+                None => (1, 0, skip),
+            };
+            let color_index = color_index % colors.len();
+            combos.push(ComboInfo {
+                combo_number,
+                index_in_combo,
+                color_index,
+                color: colors[color_index],
+            });
+        }
+        combos
+    }
+}
@@ -1,6 +1,8 @@
+use std::fmt;
+
 use osu_types::BeatmapFileSection;
 
-pub type OsuParserResult<T> = Result<T, (Option<BeatmapFileSection>, OsuParserError)>;
+pub type OsuParserResult<T> = Result<T, ParseError>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum OsuParserError {
@@ -31,9 +33,137 @@ pub enum OsuParserError {
     #[error("Invalid key '{0}' in section")]
     InvalidKey(String),
 
+    #[error("Missing required key '{0}'")]
+    MissingKey(&'static str),
+
     #[error("Unable to parse integer '{0}'")]
     IntegerParse(#[from] std::num::ParseIntError),
 
     #[error("Unable to parse float '{0}'")]
     FloatParse(#[from] std::num::ParseFloatError),
 }
+
+/// An error inside a single line. It gets turned into a [`ParseError`] once the position of the
+/// line in the file is known.
+#[derive(Debug)]
+pub struct LineError {
+    pub error: OsuParserError,
+    /// Index of the offending field, counting from 0.
+    pub field: Option<usize>,
+    /// Byte offset of the offending field in the line.
+    pub offset: Option<usize>,
+    pub expected: Option<&'static str>,
+}
+
+impl LineError {
+    pub(crate) fn at(
+        self,
+        section: Option<BeatmapFileSection>,
+        line_number: usize,
+        line: &str,
+    ) -> ParseError {
+        ParseError {
+            error: self.error,
+            section,
+            line_number: Some(line_number),
+            line: Some(line.into()),
+            field: self.field,
+            column: self
+                .offset
+                .map(|offset| line[..offset.min(line.len())].chars().count() + 1),
+            expected: self.expected,
+        }
+    }
+}
+
+impl From<OsuParserError> for LineError {
+    fn from(error: OsuParserError) -> Self {
+        LineError {
+            error,
+            field: None,
+            offset: None,
+            expected: None,
+        }
+    }
+}
+
+/// An error with as much information about where it happened as is known.
+#[derive(Debug)]
+pub struct ParseError {
+    pub error: OsuParserError,
+    pub section: Option<BeatmapFileSection>,
+    /// Line number in the file, counting from 1.
+    pub line_number: Option<usize>,
+    pub line: Option<Box<str>>,
+    /// Index of the offending field in the line, counting from 0.
+    pub field: Option<usize>,
+    /// Column of the offending field in the line, counting from 1.
+    pub column: Option<usize>,
+    /// Description of what should have been there.
+    pub expected: Option<&'static str>,
+}
+
+impl ParseError {
+    pub(crate) fn in_section(mut self, section: BeatmapFileSection) -> Self {
+        self.section = Some(section);
+        self
+    }
+}
+
+impl From<OsuParserError> for ParseError {
+    fn from(error: OsuParserError) -> Self {
+        ParseError {
+            error,
+            section: None,
+            line_number: None,
+            line: None,
+            field: None,
+            column: None,
+            expected: None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(section) = self.section {
+            write!(f, " in section {:?}", section)?;
+        }
+        if let Some(line_number) = self.line_number {
+            write!(f, " at line {}", line_number)?;
+            if let Some(column) = self.column {
+                write!(f, ", column {}", column)?;
+            }
+            if let Some(field) = self.field {
+                write!(f, " (field {})", field)?;
+            }
+        }
+
+        if let (Some(line_number), Some(line)) = (self.line_number, &self.line) {
+            let gutter = " ".repeat(line_number.to_string().len());
+            writeln!(f)?;
+            writeln!(f, "{} |", gutter)?;
+            write!(f, "{} | {}", line_number, line)?;
+            if self.column.is_some() || self.expected.is_some() {
+                writeln!(f)?;
+                write!(f, "{} | ", gutter)?;
+                if let Some(column) = self.column {
+                    write!(f, "{}^ ", " ".repeat(column - 1))?;
+                }
+                if let Some(expected) = self.expected {
+                    write!(f, "expected {}", expected)?;
+                }
+            }
+        } else if let Some(expected) = self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
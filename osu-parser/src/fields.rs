@@ -0,0 +1,154 @@
+use std::{borrow::Cow, str::FromStr};
+
+use crate::error::{LineError, OsuParserError};
+
+/// Byte offset of `part` in `line`. `part` has to be a slice of `line`.
+fn offset_in(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize
+}
+
+/// `part` of `line` as a string that lives as long as the data of the line. `part` has to be a
+/// slice of `line`.
+pub(crate) fn slice_of<'a>(line: &Cow<'a, str>, part: &str) -> Cow<'a, str> {
+    match line {
+        Cow::Borrowed(line) => {
+            let start = offset_in(line, part);
+            Cow::Borrowed(&line[start..start + part.len()])
+        }
+        Cow::Owned(_) => Cow::Owned(part.to_owned()),
+    }
+}
+
+/// Reads the separated fields of a line one by one, keeping track of where each field is so
+/// errors can point at it.
+pub(crate) struct Fields<'a> {
+    line: &'a str,
+    split: std::str::Split<'a, char>,
+    skip_empty: bool,
+    next_index: usize,
+    current: Option<(usize, &'a str)>,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(line: &'a str, separator: char) -> Self {
+        Self {
+            line,
+            split: line.split(separator),
+            skip_empty: false,
+            next_index: 0,
+            current: None,
+        }
+    }
+
+    /// Ignore empty fields, like the ones left by a trailing separator.
+    pub fn skip_empty(mut self) -> Self {
+        self.skip_empty = true;
+        self
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        let mut split = self.split.clone();
+        if self.skip_empty {
+            split.all(|field| field.is_empty())
+        } else {
+            split.next().is_none()
+        }
+    }
+
+    pub fn next_opt(&mut self) -> Option<&'a str> {
+        loop {
+            let field = self.split.next()?;
+            let index = self.next_index;
+            self.next_index += 1;
+            if self.skip_empty && field.is_empty() {
+                continue;
+            }
+            self.current = Some((index, field));
+            return Some(field);
+        }
+    }
+
+    pub fn next(&mut self, expected: &'static str) -> Result<&'a str, LineError> {
+        self.next_opt().ok_or(LineError {
+            error: OsuParserError::BadFormat,
+            field: Some(self.next_index),
+            offset: Some(self.line.len()),
+            expected: Some(expected),
+        })
+    }
+
+    pub fn parse<T>(&mut self, expected: &'static str) -> Result<T, LineError>
+    where
+        T: FromStr,
+        T::Err: Into<OsuParserError>,
+    {
+        let field = self.next(expected)?;
+        field.parse().map_err(|e| self.error(e, expected))
+    }
+
+    pub fn parse_opt<T>(&mut self, expected: &'static str) -> Result<Option<T>, LineError>
+    where
+        T: FromStr,
+        T::Err: Into<OsuParserError>,
+    {
+        self.next_opt()
+            .map(|field| field.parse().map_err(|e| self.error(e, expected)))
+            .transpose()
+    }
+
+    /// An error about the field that was read last.
+    pub fn error(&self, error: impl Into<OsuParserError>, expected: &'static str) -> LineError {
+        LineError {
+            error: error.into(),
+            field: self.current.map(|(index, _)| index),
+            offset: self.current.map(|(_, field)| offset_in(self.line, field)),
+            expected: Some(expected),
+        }
+    }
+}
+
+/// A `key: value` line.
+pub(crate) struct KeyValue<'a> {
+    line: &'a str,
+    pub key: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> KeyValue<'a> {
+    pub fn new(line: &'a str) -> Result<Self, LineError> {
+        let (key, value) = crate::split_key_value(line).ok_or(LineError {
+            error: OsuParserError::BadFormat,
+            field: None,
+            offset: None,
+            expected: Some("a 'key: value' pair"),
+        })?;
+        Ok(Self { line, key, value })
+    }
+
+    pub fn parse<T>(&self, expected: &'static str) -> Result<T, LineError>
+    where
+        T: FromStr,
+        T::Err: Into<OsuParserError>,
+    {
+        self.value.parse().map_err(|e| self.error(e, expected))
+    }
+
+    /// An error about the value.
+    pub fn error(&self, error: impl Into<OsuParserError>, expected: &'static str) -> LineError {
+        LineError {
+            error: error.into(),
+            field: Some(1),
+            offset: Some(offset_in(self.line, self.value)),
+            expected: Some(expected),
+        }
+    }
+
+    pub fn invalid_key(&self) -> LineError {
+        LineError {
+            error: OsuParserError::InvalidKey(self.key.to_owned()),
+            field: Some(0),
+            offset: Some(offset_in(self.line, self.key)),
+            expected: None,
+        }
+    }
+}
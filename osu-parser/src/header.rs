@@ -0,0 +1,114 @@
+use std::{borrow::Cow, io::BufRead};
+
+use osu_types::{BeatmapInfo, HitObjectType, Time};
+
+use crate::{
+    error::{LineError, OsuParserError, OsuParserResult},
+    fields::Fields,
+    parse_lines, BeatmapParseOptions,
+};
+
+#[derive(Default)]
+pub struct HeaderScanOptions {
+    /// Also go through `[HitObjects]` to fill in [`BeatmapHeader::hit_objects`]. Only the time
+    /// and type of each object are read.
+    pub count_hit_objects: bool,
+    pub lenient: bool,
+}
+
+/// Object counts of a beatmap, without parsing the objects.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HitObjectSummary {
+    pub circles: u32,
+    pub sliders: u32,
+    pub spinners: u32,
+    pub holds: u32,
+    /// Start time of the first object.
+    pub first_time: Option<Time>,
+    /// Start time of the last object.
+    pub last_time: Option<Time>,
+}
+
+impl HitObjectSummary {
+    pub fn count(&self) -> u32 {
+        self.circles + self.sliders + self.spinners + self.holds
+    }
+
+    pub(crate) fn add_line(&mut self, line: &str) -> Result<(), LineError> {
+        let mut fields = Fields::new(line, ',');
+        fields.next("x position")?;
+        fields.next("y position")?;
+        let time: Time = fields.parse("time")?;
+        let ty = HitObjectType::from_bits_retain(fields.parse("object type")?);
+        if ty.contains(HitObjectType::CIRCLE) {
+            self.circles += 1;
+        } else if ty.contains(HitObjectType::SLIDER) {
+            self.sliders += 1;
+        } else if ty.contains(HitObjectType::SPINNER) {
+            self.spinners += 1;
+        } else if ty.contains(HitObjectType::MANIA_HOLD) {
+            self.holds += 1;
+        } else {
+            return Err(fields.error(
+                OsuParserError::BadFormat,
+                "object type with the circle, slider, spinner or hold bit set",
+            ));
+        }
+        self.first_time.get_or_insert(time);
+        self.last_time = Some(time);
+        Ok(())
+    }
+}
+
+/// What is needed to list a beatmap, see [`scan_header`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatmapHeader {
+    pub info: BeatmapInfo,
+    /// Only set if [`HeaderScanOptions::count_hit_objects`] was set.
+    pub hit_objects: Option<HitObjectSummary>,
+}
+
+/// Reads only `[General]`, `[Metadata]` and `[Difficulty]`, and stops as soon as they are done.
+/// Hit objects are only counted if asked for.
+pub fn scan_header(content: &str, options: HeaderScanOptions) -> OsuParserResult<BeatmapHeader> {
+    scan_lines(content.lines().map(|line| Ok(Cow::Borrowed(line))), options)
+}
+
+pub fn scan_header_reader(
+    reader: impl BufRead,
+    options: HeaderScanOptions,
+) -> OsuParserResult<BeatmapHeader> {
+    scan_lines(reader.lines().map(|line| line.map(Cow::Owned)), options)
+}
+
+pub fn scan_header_file(
+    path: impl AsRef<std::path::Path>,
+    options: HeaderScanOptions,
+) -> OsuParserResult<BeatmapHeader> {
+    let file = std::fs::File::open(path).map_err(OsuParserError::from)?;
+    scan_header_reader(std::io::BufReader::new(file), options)
+}
+
+fn scan_lines<'a>(
+    lines: impl Iterator<Item = std::io::Result<Cow<'a, str>>>,
+    options: HeaderScanOptions,
+) -> OsuParserResult<BeatmapHeader> {
+    let parse_options = BeatmapParseOptions {
+        read_editor: false,
+        read_events: false,
+        read_colors: false,
+        read_timing_points: false,
+        read_hit_objects: false,
+        lenient: options.lenient,
+    };
+    let mut summary = HitObjectSummary::default();
+    let (beatmap, _) = parse_lines(
+        lines,
+        parse_options,
+        options.count_hit_objects.then_some(&mut summary),
+    )?;
+    Ok(BeatmapHeader {
+        info: beatmap.into_owned().info,
+        hit_objects: Some(summary).filter(|_| options.count_hit_objects),
+    })
+}
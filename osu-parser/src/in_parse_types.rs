@@ -1,21 +1,37 @@
-use osu_types::{Countdown, Mode, SampleSet};
+use std::borrow::Cow;
+
+use osu_types::{ComboColor, Countdown, Mode, OverlayPosition, SampleSet, Time, RGB};
 
 #[derive(Default)]
 pub struct InParseGeneral<'a> {
-    pub audio_file_name: Option<&'a str>,
-    pub audio_lead_in: Option<u32>,
-    pub preview_time: Option<u32>,
-    pub countdown: Option<Countdown>,
+    pub audio_file_name: Option<Cow<'a, str>>,
+    pub audio_lead_in: Option<Time>,
+    pub preview_time: Option<Time>,
+    /// `Some(None)` if the beatmap turns the countdown off.
+    pub countdown: Option<Option<Countdown>>,
     pub sample_set: Option<SampleSet>,
     pub stack_leniency: Option<f32>,
     pub mode: Option<Mode>,
     pub letterbox_in_breaks: Option<bool>,
+    pub story_fire_in_front: Option<bool>,
+    pub use_skin_sprites: Option<bool>,
+    pub always_show_playfield: Option<bool>,
+    pub overlay_position: Option<OverlayPosition>,
+    pub skin_preference: Option<Cow<'a, str>>,
+    pub epilepsy_warning: Option<bool>,
+    pub countdown_offset: Option<u32>,
+    pub special_style: Option<bool>,
     pub widescreen_storyboard: Option<bool>,
+    pub samples_match_playback_rate: Option<bool>,
+    pub audio_hash: Option<Cow<'a, str>>,
+    // Old files keep these in [General] instead of [Editor].
+    pub editor_bookmarks: Option<Vec<Time>>,
+    pub editor_distance_spacing: Option<f32>,
 }
 
 #[derive(Default)]
 pub struct InParseEditor {
-    pub bookmarks: Vec<u32>,
+    pub bookmarks: Vec<Time>,
     pub distance_spacing: Option<f32>,
     pub beat_divisor: Option<u8>,
     pub grid_size: Option<u8>,
@@ -24,14 +40,14 @@ pub struct InParseEditor {
 
 #[derive(Default)]
 pub struct InParseMetadata<'a> {
-    pub title: Option<&'a str>,
-    pub title_unicode: Option<&'a str>,
-    pub artist: Option<&'a str>,
-    pub artist_unicode: Option<&'a str>,
-    pub creator: Option<&'a str>,
-    pub version: Option<&'a str>,
-    pub source: Option<&'a str>,
-    pub tags: Option<&'a str>,
+    pub title: Option<Cow<'a, str>>,
+    pub title_unicode: Option<Cow<'a, str>>,
+    pub artist: Option<Cow<'a, str>>,
+    pub artist_unicode: Option<Cow<'a, str>>,
+    pub creator: Option<Cow<'a, str>>,
+    pub version: Option<Cow<'a, str>>,
+    pub source: Option<Cow<'a, str>>,
+    pub tags: Option<Cow<'a, str>>,
     pub beatmap_id: Option<i32>,
     pub beatmap_set_id: Option<i32>,
 }
@@ -45,3 +61,10 @@ pub struct InParseDifficulty {
     pub slider_multiplier: Option<f32>,
     pub slider_tick_rate: Option<f32>,
 }
+
+#[derive(Default)]
+pub struct InParseColors {
+    pub combo_colors: Vec<(u32, ComboColor)>,
+    pub slider_track_override: Option<RGB<u8>>,
+    pub slider_border: Option<RGB<u8>>,
+}
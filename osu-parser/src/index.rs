@@ -0,0 +1,119 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use rayon::{iter::Either, prelude::*};
+use walkdir::WalkDir;
+
+use crate::{
+    error::{OsuParserError, ParseError},
+    scan_header_file, BeatmapHeader, HeaderScanOptions,
+};
+
+#[derive(Default)]
+pub struct IndexOptions {
+    /// Count the hit objects of every beatmap, see [`HeaderScanOptions::count_hit_objects`].
+    pub count_hit_objects: bool,
+    pub lenient: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedBeatmap {
+    pub path: PathBuf,
+    pub header: BeatmapHeader,
+}
+
+/// Difficulties that share a folder and a beatmap set id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedBeatmapSet {
+    pub folder: PathBuf,
+    /// `None` for beatmaps that don't have a set id (older than v10, or never submitted).
+    pub beatmap_set_id: Option<i32>,
+    pub beatmaps: Vec<IndexedBeatmap>,
+}
+
+/// A file or folder that could not be indexed.
+#[derive(Debug)]
+pub struct IndexError {
+    pub path: PathBuf,
+    pub error: ParseError,
+}
+
+#[derive(Debug, Default)]
+pub struct SongsIndex {
+    /// Sorted by folder, then by set id.
+    pub sets: Vec<IndexedBeatmapSet>,
+    pub errors: Vec<IndexError>,
+}
+
+impl SongsIndex {
+    pub fn beatmap_count(&self) -> usize {
+        self.sets.iter().map(|set| set.beatmaps.len()).sum()
+    }
+}
+
+/// Finds every .osu file below `root` and scans their headers in parallel on the rayon thread
+/// pool. To limit the number of threads, call this from [`rayon::ThreadPool::install`].
+///
+/// Files that fail to parse end up in [`SongsIndex::errors`] and don't stop the scan.
+pub fn index_songs(root: impl AsRef<Path>, options: IndexOptions) -> SongsIndex {
+    let mut index = SongsIndex::default();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(root) {
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_file() && is_osu_file(entry.path()) {
+                    paths.push(entry.into_path());
+                }
+            }
+            Err(error) => index.errors.push(IndexError {
+                path: error.path().map(Path::to_owned).unwrap_or_default(),
+                error: OsuParserError::IOError(error.into()).into(),
+            }),
+        }
+    }
+
+    let (beatmaps, errors): (Vec<_>, Vec<_>) = paths.into_par_iter().partition_map(|path| {
+        let header_options = HeaderScanOptions {
+            count_hit_objects: options.count_hit_objects,
+            lenient: options.lenient,
+        };
+        match scan_header_file(&path, header_options) {
+            Ok(header) => Either::Left(IndexedBeatmap { path, header }),
+            Err(error) => Either::Right(IndexError { path, error }),
+        }
+    });
+    index.errors.extend(errors);
+
+    let mut sets: BTreeMap<(PathBuf, Option<i32>), Vec<IndexedBeatmap>> = BTreeMap::new();
+    for beatmap in beatmaps {
+        let folder = beatmap
+            .path
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+        let set_id = Some(beatmap.header.info.metadata.beatmap_set_id).filter(|&id| id > 0);
+        sets.entry((folder, set_id)).or_default().push(beatmap);
+    }
+
+    index.sets = sets
+        .into_iter()
+        .map(|((folder, beatmap_set_id), mut beatmaps)| {
+            beatmaps.sort_by(|a, b| a.path.cmp(&b.path));
+            IndexedBeatmapSet {
+                folder,
+                beatmap_set_id,
+                beatmaps,
+            }
+        })
+        .collect();
+    index.errors.sort_by(|a, b| a.path.cmp(&b.path));
+    index
+}
+
+fn is_osu_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("osu"))
+}
@@ -1,32 +1,140 @@
-use std::num::ParseIntError;
+use std::{borrow::Cow, io::BufRead};
 
 use error::*;
+use fields::{slice_of, Fields, KeyValue};
 use in_parse_types::*;
+use osu_types::storyboard::Storyboard;
 use osu_types::{
-    BeatmapDifficulty, BeatmapFileSection, BeatmapGeneralData, BeatmapInfo, BeatmapMetadata,
-    ComboColor, Countdown, CurveType, Event, HitObject, HitSample, Mode, OsuPoint, SampleSet,
-    SpecificHitObject, TimingPoint,
+    BeatmapDifficulty, BeatmapEditor, BeatmapFileSection, BeatmapInfo, ComboColor, Countdown,
+    CurveType, EdgeSet, Effects, Event, HitObject, HitObjectType, HitSound, Mode, OsuPoint,
+    OverlayPosition, SampleSet, SpecificHitObject, Time, TimingPoint, RGB,
 };
+use storyboard::{expand_variables, read_storyboard_line, read_variable_line};
 
+mod borrowed;
+mod checksum;
+mod combo;
 pub mod error;
+mod fields;
+mod header;
 mod in_parse_types;
+#[cfg(feature = "index")]
+mod index;
+#[cfg(feature = "osz")]
+mod osz;
+mod set;
+mod stacking;
+mod statistics;
+mod storyboard;
+mod writer;
+
+pub use borrowed::{
+    AnimationRef, BeatmapGeneralDataRef, BeatmapMetadataRef, BeatmapRef, EventRef, HitObjectRef,
+    HitSampleRef, SampleRef, SpriteRef, StoryboardObjectRef, StoryboardRef,
+};
+pub use checksum::{checksum, checksum_hex, load_bytes, load_file_with_checksum};
+pub use combo::ComboInfo;
+pub use header::{
+    scan_header, scan_header_file, scan_header_reader, BeatmapHeader, HeaderScanOptions,
+    HitObjectSummary,
+};
+#[cfg(feature = "index")]
+pub use index::{
+    index_songs, IndexError, IndexOptions, IndexedBeatmap, IndexedBeatmapSet, SongsIndex,
+};
+#[cfg(feature = "osz")]
+pub use osz::OszArchive;
+pub use set::{AssetKind, BeatmapSet, FileReference, SetDifficulty};
+pub use statistics::BeatmapStatistics;
+pub use storyboard::{load_storyboard_content, load_storyboard_file};
+pub use writer::{save_content, save_file, write_beatmap, BeatmapWriteOptions};
+
+/// Oldest file format version that can be read and written.
+pub const MIN_FORMAT_VERSION: u8 = 3;
+/// Newest file format version that can be read and written.
+pub const MAX_FORMAT_VERSION: u8 = 14;
+
+/// Combo colours used by beatmaps without a `[Colours]` section.
+pub const DEFAULT_COMBO_COLORS: [ComboColor; 4] = [
+    ComboColor {
+        r: u8::MAX,
+        g: 0,
+        b: 0,
+    },
+    ComboColor {
+        r: 0,
+        g: u8::MAX,
+        b: 0,
+    },
+    ComboColor {
+        r: 0,
+        g: 0,
+        b: u8::MAX,
+    },
+    ComboColor {
+        r: u8::MAX,
+        g: 0,
+        b: u8::MAX,
+    },
+];
 
 // TODO make these optional at runtime
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Beatmap {
+    /// File format version the beatmap was read from. Some things, like combos and stacking,
+    /// work differently in old versions.
+    pub format_version: u8,
     pub info: BeatmapInfo,
+    pub editor: BeatmapEditor,
     pub events: Vec<Event>,
+    pub storyboard: Storyboard,
+    /// Combo colours from the `[Colours]` section, empty if there are none. See
+    /// [`Beatmap::combo_colors`] for the colours that are actually used.
     pub colors: Vec<ComboColor>,
+    pub slider_track_override: Option<RGB<u8>>,
+    pub slider_border: Option<RGB<u8>>,
     pub timing_points: Vec<TimingPoint>,
     pub hit_objects: Vec<HitObject>,
 }
 
+impl Beatmap {
+    /// The combo colours of the beatmap, or [`DEFAULT_COMBO_COLORS`] if it doesn't have any.
+    pub fn combo_colors(&self) -> &[ComboColor] {
+        if self.colors.is_empty() {
+            &DEFAULT_COMBO_COLORS
+        } else {
+            &self.colors
+        }
+    }
+
+    /// File name of the background image, if the beatmap has one.
+    pub fn background_file_name(&self) -> Option<&str> {
+        self.events.iter().find_map(|event| match event {
+            Event::Background { filename, .. } => Some(filename.as_str()),
+            _ => None,
+        })
+    }
+
+    /// File name of the background video, if the beatmap has one.
+    pub fn video_file_name(&self) -> Option<&str> {
+        self.events.iter().find_map(|event| match event {
+            Event::Video { filename, .. } => Some(filename.as_str()),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct BeatmapParseOptions {
     pub read_editor: bool,
     pub read_events: bool,
     pub read_colors: bool,
     pub read_timing_points: bool,
     pub read_hit_objects: bool,
+    /// Skip lines that can't be read and fill in missing values with defaults instead of failing,
+    /// like the client does. What was skipped is reported as warnings.
+    pub lenient: bool,
 }
 
 impl Default for BeatmapParseOptions {
@@ -34,9 +142,10 @@ impl Default for BeatmapParseOptions {
         Self {
             read_editor: true,
             read_events: true,
-            read_colors: false,
+            read_colors: true,
             read_timing_points: true,
             read_hit_objects: true,
+            lenient: false,
         }
     }
 }
@@ -50,376 +159,669 @@ pub fn load_file(
     path: impl AsRef<std::path::Path>,
     options: BeatmapParseOptions,
 ) -> OsuParserResult<Beatmap> {
-    let content = std::fs::read_to_string(path).unwrap();
-    load_content(&content, options)
+    load_file_with_warnings(path, options).map(|(beatmap, _)| beatmap)
+}
+
+/// Like [`load_file`], but also returns the problems that were skipped in lenient mode.
+pub fn load_file_with_warnings(
+    path: impl AsRef<std::path::Path>,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(Beatmap, Vec<ParseError>)> {
+    let file = std::fs::File::open(path).map_err(OsuParserError::from)?;
+    load_reader_with_warnings(std::io::BufReader::new(file), options)
+}
+
+pub fn load_content(content: &str, options: BeatmapParseOptions) -> OsuParserResult<Beatmap> {
+    load_content_with_warnings(content, options).map(|(beatmap, _)| beatmap)
+}
+
+/// Like [`load_content`], but also returns the problems that were skipped in lenient mode.
+pub fn load_content_with_warnings(
+    content: &str,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(Beatmap, Vec<ParseError>)> {
+    load_content_ref_with_warnings(content, options)
+        .map(|(beatmap, warnings)| (beatmap.into_owned(), warnings))
+}
+
+/// Like [`load_content`], but borrows strings from `content` instead of copying them. See
+/// [`BeatmapRef`] for what is borrowed.
+pub fn load_content_ref(
+    content: &str,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<BeatmapRef<'_>> {
+    load_content_ref_with_warnings(content, options).map(|(beatmap, _)| beatmap)
+}
+
+/// Like [`load_content_ref`], but also returns the problems that were skipped in lenient mode.
+pub fn load_content_ref_with_warnings(
+    content: &str,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(BeatmapRef<'_>, Vec<ParseError>)> {
+    parse_lines(
+        content.lines().map(|line| Ok(Cow::Borrowed(line))),
+        options,
+        None,
+    )
+}
+
+/// Reads a beatmap line by line. Reading stops as soon as all sections that `options` asks for
+/// have been read, so the rest of the input is never touched.
+pub fn load_reader(reader: impl BufRead, options: BeatmapParseOptions) -> OsuParserResult<Beatmap> {
+    load_reader_with_warnings(reader, options).map(|(beatmap, _)| beatmap)
+}
+
+/// Like [`load_reader`], but also returns the problems that were skipped in lenient mode.
+pub fn load_reader_with_warnings(
+    reader: impl BufRead,
+    options: BeatmapParseOptions,
+) -> OsuParserResult<(Beatmap, Vec<ParseError>)> {
+    parse_lines(
+        reader.lines().map(|line| line.map(Cow::Owned)),
+        options,
+        None,
+    )
+    .map(|(beatmap, warnings)| (beatmap.into_owned(), warnings))
 }
 
 #[derive(Default)]
 struct ParseData<'a> {
+    format_version: u8,
     general: InParseGeneral<'a>,
     editor: InParseEditor,
     metadata: InParseMetadata<'a>,
     difficulty: InParseDifficulty,
-    events: Vec<Event>,
+    events: Vec<EventRef<'a>>,
+    storyboard: StoryboardRef<'a>,
+    variables: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     timing_points: Vec<TimingPoint>,
-    colors: Vec<ComboColor>,
-    hit_objects: Vec<HitObject>,
+    colors: InParseColors,
+    hit_objects: Vec<HitObjectRef<'a>>,
 }
 
-pub fn load_content(content: &str, options: BeatmapParseOptions) -> OsuParserResult<Beatmap> {
-    let mut lines = content
-        .lines()
-        .filter(|&line| !line.trim().is_empty() && !line.starts_with("//"));
+/// Collects the errors that lenient mode recovers from.
+struct Warnings {
+    lenient: bool,
+    warnings: Vec<ParseError>,
+}
 
-    let version_string: &str = lines
-        .next()
-        .ok_or((None, OsuParserError::BadFormat))?
-        .trim_start_matches("osu file format v");
-    let version: u8 = version_string
-        .parse()
-        .map_err(|e: ParseIntError| (None, e.into()))?;
-
-    if version < 3 {
-        return Err((None, OsuParserError::VersionTooOld(version)));
-    } else if version > 14 {
-        return Err((None, OsuParserError::VersionTooNew(version)));
+impl Warnings {
+    /// Turns the error into a warning in lenient mode.
+    fn check<T>(&mut self, result: Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.lenient => {
+                self.warnings.push(error);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// A value that has to be in the file, `default` is only used in lenient mode.
+    fn required<T>(
+        &mut self,
+        value: Option<T>,
+        section: BeatmapFileSection,
+        key: &'static str,
+        default: T,
+    ) -> Result<T, ParseError> {
+        let value = value.ok_or_else(|| missing_key(section, key));
+        Ok(self.check(value)?.unwrap_or(default))
+    }
+}
+
+/// The sections that have to be read for the given options.
+fn wanted_sections(options: &BeatmapParseOptions) -> Vec<BeatmapFileSection> {
+    use BeatmapFileSection::*;
+    let mut sections = vec![General, Metadata, Difficulty];
+    let optional = [
+        (options.read_editor, vec![Editor]),
+        // [Variables] is still read if it comes first, which is the only place it matters for
+        // [Events], but isn't waited for.
+        (options.read_events, vec![Events]),
+        (options.read_colors, vec![Colors]),
+        (options.read_timing_points, vec![TimingPoints]),
+        (options.read_hit_objects, vec![HitObjects]),
+    ];
+    for (read, optional_sections) in optional {
+        if read {
+            sections.extend(optional_sections);
+        }
     }
+    sections
+}
 
+/// Parses the lines of a .osu file. If `summary` is given, hit objects are only counted into it
+/// instead of being read.
+pub(crate) fn parse_lines<'a>(
+    lines: impl Iterator<Item = std::io::Result<Cow<'a, str>>>,
+    options: BeatmapParseOptions,
+    mut summary: Option<&mut HitObjectSummary>,
+) -> OsuParserResult<(BeatmapRef<'a>, Vec<ParseError>)> {
+    let mut warnings = Warnings {
+        lenient: options.lenient,
+        warnings: Vec::new(),
+    };
+    let mut lines = lines
+        .enumerate()
+        .map(|(i, line)| {
+            line.map(|line| (i + 1, line)).map_err(|e| ParseError {
+                line_number: Some(i + 1),
+                ..OsuParserError::from(e).into()
+            })
+        })
+        .filter(|line| match line {
+            Ok((_, line)) => !line.trim().is_empty() && !line.starts_with("//"),
+            Err(_) => true,
+        });
+
+    let (line_number, version_line) = lines.next().ok_or(OsuParserError::BadFormat)??;
+    let format_version = warnings.check(
+        read_version_line(&version_line).map_err(|e| e.at(None, line_number, &version_line)),
+    )?;
+
+    let mut unread_sections = wanted_sections(&options);
+    if summary.is_some() {
+        unread_sections.push(BeatmapFileSection::HitObjects);
+    }
     let mut current_section: Option<BeatmapFileSection> = None;
-    let mut data = ParseData::default();
+    // Set after an unknown section in lenient mode, so its lines are skipped without a warning
+    // for each of them.
+    let mut skipping_section = false;
+    let mut data = ParseData {
+        // Like the client, a beatmap without a readable version is taken to be current.
+        format_version: format_version.unwrap_or(MAX_FORMAT_VERSION),
+        ..ParseData::default()
+    };
 
     for line in lines {
-        if line.starts_with("[") && line.ends_with("]") {
-            let section_string = &line[1..line.len() - 1];
-            let section = BeatmapFileSection::from_str(section_string).ok_or_else(|| {
-                (
-                    None,
-                    OsuParserError::InvalidSection(section_string.to_owned()),
-                )
-            })?;
-            current_section = Some(section);
+        let (line_number, line) = line?;
+        if line.starts_with('[') && line.ends_with(']') {
+            if unread_sections.is_empty() {
+                break;
+            }
+            current_section = warnings
+                .check(read_section_line(&line).map_err(|e| e.at(None, line_number, &line)))?;
+            skipping_section = current_section.is_none();
+            unread_sections.retain(|&section| Some(section) != current_section);
             continue;
         }
 
-        let current_section = current_section.ok_or((None, OsuParserError::DataOutsideSection))?;
-        match current_section {
+        let section = match current_section {
+            Some(section) => section,
+            None if skipping_section => continue,
+            None => {
+                warnings.check::<()>(Err(LineError::from(OsuParserError::DataOutsideSection)
+                    .at(None, line_number, &line)))?;
+                continue;
+            }
+        };
+        let at = |e: LineError| e.at(Some(section), line_number, &line);
+        match section {
             BeatmapFileSection::General => {
-                read_general_line(&mut data.general, line)
-                    .map_err(|e| (Some(BeatmapFileSection::General), e))?;
+                warnings.check(read_general_line(&mut data.general, &line).map_err(at))?;
             }
             BeatmapFileSection::Editor => {
                 if options.read_editor {
-                    read_editor_line(&mut data.editor, line)
-                        .map_err(|e| (Some(BeatmapFileSection::Editor), e))?;
+                    warnings.check(read_editor_line(&mut data.editor, &line).map_err(at))?;
                 };
             }
             BeatmapFileSection::Metadata => {
-                read_metadata_line(&mut data.metadata, line)
-                    .map_err(|e| (Some(BeatmapFileSection::Metadata), e))?;
+                warnings.check(read_metadata_line(&mut data.metadata, &line).map_err(at))?;
             }
             BeatmapFileSection::Difficulty => {
-                read_difficulty_line(&mut data.difficulty, line)
-                    .map_err(|e| (Some(BeatmapFileSection::Difficulty), e))?;
+                warnings.check(read_difficulty_line(&mut data.difficulty, &line).map_err(at))?;
             }
             BeatmapFileSection::Colors => {
                 if options.read_colors {
-                    //let (key, value) = split_key_value(line).ok_or(OsuParserError::BadFormat)?;
-                    todo!()
+                    warnings.check(read_colors_line(&mut data.colors, &line).map_err(at))?;
                 };
             }
             BeatmapFileSection::Events => {
                 if options.read_events {
-                    let event =
-                        read_event_line(line).map_err(|e| (Some(BeatmapFileSection::Events), e))?;
-                    data.events.push(event);
+                    let line = expand_variables(&data.variables, line);
+                    warnings.check(
+                        read_event_line(&mut data.events, &mut data.storyboard, &line)
+                            .map_err(|e| e.at(Some(section), line_number, &line)),
+                    )?;
                 };
             }
             BeatmapFileSection::TimingPoints => {
                 if options.read_timing_points {
-                    let timing_point = read_timing_point_line(line)
-                        .map_err(|e| (Some(BeatmapFileSection::TimingPoints), e))?;
-                    data.timing_points.push(timing_point);
+                    let timing_point = warnings.check(read_timing_point_line(&line).map_err(at))?;
+                    data.timing_points.extend(timing_point);
                 };
             }
             BeatmapFileSection::HitObjects => {
-                if options.read_hit_objects {
-                    let hit_object = read_hitobject_line(line)
-                        .map_err(|e| (Some(BeatmapFileSection::HitObjects), e))?;
-                    data.hit_objects.push(hit_object);
+                if let Some(summary) = summary.as_deref_mut() {
+                    warnings.check(summary.add_line(&line).map_err(at))?;
+                } else if options.read_hit_objects {
+                    let hit_object = warnings.check(read_hitobject_line(&line).map_err(at))?;
+                    data.hit_objects.extend(hit_object);
+                };
+            }
+            BeatmapFileSection::Variables => {
+                if options.read_events {
+                    warnings.check(read_variable_line(&mut data.variables, &line).map_err(at))?;
                 };
             }
         }
     }
 
-    Ok(finalize_parse(data).map_err(|e| (None, e))?)
-}
-
-fn finalize_parse(data: ParseData) -> Result<Beatmap, OsuParserError> {
-    let title = data.metadata.title.ok_or(OsuParserError::BadFormat)?;
-    let artist = data.metadata.artist.ok_or(OsuParserError::BadFormat)?;
-    let od = data.difficulty.od.ok_or(OsuParserError::BadFormat)?;
-    Ok(Beatmap {
-        info: BeatmapInfo {
-            general_data: BeatmapGeneralData {
-                audio_file_name: data
-                    .general
-                    .audio_file_name
-                    .ok_or(OsuParserError::BadFormat)?
-                    .to_owned(),
-                audio_lead_in: data.general.audio_lead_in.unwrap_or(0),
-                preview_time: data.general.preview_time.unwrap_or(0),
-                countdown: data.general.countdown,
-                sample_set: data.general.sample_set.unwrap_or(SampleSet::Normal),
-                stack_leniency: data.general.stack_leniency.unwrap_or(0.5),
-                mode: data.general.mode.unwrap_or(Mode::Osu),
-                letterbox_in_breaks: data.general.letterbox_in_breaks.unwrap_or(false),
-                widescreen_storyboard: data.general.widescreen_storyboard.unwrap_or(false),
-            },
-            metadata: BeatmapMetadata {
-                title: title.to_owned(),
-                title_unicode: data.metadata.title_unicode.unwrap_or(title).to_owned(),
-                artist: artist.to_owned(),
-                artist_unicode: data.metadata.artist_unicode.unwrap_or(artist).to_owned(),
-                creator: data
-                    .metadata
-                    .creator
-                    .ok_or(OsuParserError::BadFormat)?
-                    .to_owned(),
-                version: data
-                    .metadata
-                    .version
-                    .ok_or(OsuParserError::BadFormat)?
-                    .to_owned(),
-                source: data.metadata.source.unwrap_or("").to_owned(),
-                tags: data.metadata.tags.unwrap_or("").to_owned(),
-                beatmap_id: data.metadata.beatmap_id.unwrap_or(-1).to_owned(),
-                beatmap_set_id: data.metadata.beatmap_set_id.unwrap_or(-1).to_owned(),
-            },
-            difficulty: BeatmapDifficulty {
-                hp: data.difficulty.hp.ok_or(OsuParserError::BadFormat)?,
-                cs: data.difficulty.cs.ok_or(OsuParserError::BadFormat)?,
-                od,
-                ar: data.difficulty.ar.unwrap_or(od),
-                slider_multiplier: data
-                    .difficulty
-                    .slider_multiplier
-                    .ok_or(OsuParserError::BadFormat)?,
-                slider_tick_rate: data
-                    .difficulty
-                    .slider_tick_rate
-                    .ok_or(OsuParserError::BadFormat)?,
-            },
+    let beatmap = finalize_parse(data, &mut warnings)?;
+    Ok((beatmap, warnings.warnings))
+}
+
+fn read_version_line(line: &str) -> Result<u8, LineError> {
+    // Files saved by some editors start with a UTF-8 byte order mark.
+    let line = line.strip_prefix('\u{feff}').unwrap_or(line);
+    let version_string = line
+        .trim()
+        .strip_prefix("osu file format v")
+        .ok_or(LineError {
+            error: OsuParserError::VersionParse(line.to_owned()),
+            field: None,
+            offset: Some(0),
+            expected: Some("'osu file format v<version>'"),
+        })?;
+    let version: u8 = version_string.parse().map_err(|_| LineError {
+        error: OsuParserError::VersionParse(version_string.to_owned()),
+        field: None,
+        offset: Some(line.find('v').map_or(0, |v| v + 1)),
+        expected: Some("a version number"),
+    })?;
+
+    if version < MIN_FORMAT_VERSION {
+        Err(OsuParserError::VersionTooOld(version).into())
+    } else if version > MAX_FORMAT_VERSION {
+        Err(OsuParserError::VersionTooNew(version).into())
+    } else {
+        Ok(version)
+    }
+}
+
+pub(crate) fn read_section_line(line: &str) -> Result<BeatmapFileSection, LineError> {
+    let section_string = &line[1..line.len() - 1];
+    BeatmapFileSection::from_str(section_string).ok_or(LineError {
+        error: OsuParserError::InvalidSection(section_string.to_owned()),
+        field: None,
+        offset: Some(1),
+        expected: Some("a section name"),
+    })
+}
+
+fn missing_key(section: BeatmapFileSection, key: &'static str) -> ParseError {
+    ParseError::from(OsuParserError::MissingKey(key)).in_section(section)
+}
+
+fn finalize_parse<'a>(
+    mut data: ParseData<'a>,
+    warnings: &mut Warnings,
+) -> Result<BeatmapRef<'a>, ParseError> {
+    // Combo colours are numbered, but nothing forces them to be written in order.
+    data.colors.combo_colors.sort_by_key(|&(index, _)| index);
+    use BeatmapFileSection::{Difficulty, General, Metadata};
+    let title = warnings.required(data.metadata.title, Metadata, "Title", "".into())?;
+    let artist = warnings.required(data.metadata.artist, Metadata, "Artist", "".into())?;
+    let od = warnings.required(data.difficulty.od, Difficulty, "OverallDifficulty", 5.0)?;
+    Ok(BeatmapRef {
+        format_version: data.format_version,
+        general: BeatmapGeneralDataRef {
+            audio_file_name: warnings.required(
+                data.general.audio_file_name,
+                General,
+                "AudioFilename",
+                "".into(),
+            )?,
+            audio_lead_in: data.general.audio_lead_in.unwrap_or(0.0),
+            preview_time: data.general.preview_time,
+            countdown: data.general.countdown.unwrap_or(Some(Countdown::Normal)),
+            sample_set: data.general.sample_set.unwrap_or(SampleSet::Normal),
+            stack_leniency: data.general.stack_leniency.unwrap_or(0.7),
+            mode: data.general.mode.unwrap_or(Mode::Osu),
+            letterbox_in_breaks: data.general.letterbox_in_breaks.unwrap_or(false),
+            story_fire_in_front: data.general.story_fire_in_front.unwrap_or(true),
+            use_skin_sprites: data.general.use_skin_sprites.unwrap_or(false),
+            always_show_playfield: data.general.always_show_playfield.unwrap_or(false),
+            overlay_position: data
+                .general
+                .overlay_position
+                .unwrap_or(OverlayPosition::NoChange),
+            skin_preference: data.general.skin_preference,
+            epilepsy_warning: data.general.epilepsy_warning.unwrap_or(false),
+            countdown_offset: data.general.countdown_offset.unwrap_or(0),
+            special_style: data.general.special_style.unwrap_or(false),
+            widescreen_storyboard: data.general.widescreen_storyboard.unwrap_or(false),
+            samples_match_playback_rate: data.general.samples_match_playback_rate.unwrap_or(false),
+            audio_hash: data.general.audio_hash,
         },
-        events: data.events,
-        colors: if data.colors.len() == 0 {
-            vec![
-                ComboColor {
-                    r: u8::MAX,
-                    g: 0,
-                    b: 0,
-                },
-                ComboColor {
-                    r: 0,
-                    g: u8::MAX,
-                    b: 0,
-                },
-                ComboColor {
-                    r: 0,
-                    g: 0,
-                    b: u8::MAX,
-                },
-                ComboColor {
-                    r: u8::MAX,
-                    g: 0,
-                    b: u8::MAX,
+        metadata: BeatmapMetadataRef {
+            title_unicode: data.metadata.title_unicode.unwrap_or_else(|| title.clone()),
+            title,
+            artist_unicode: data
+                .metadata
+                .artist_unicode
+                .unwrap_or_else(|| artist.clone()),
+            artist,
+            creator: warnings.required(data.metadata.creator, Metadata, "Creator", "".into())?,
+            version: warnings.required(data.metadata.version, Metadata, "Version", "".into())?,
+            source: data.metadata.source.unwrap_or_default(),
+            tags: data.metadata.tags.unwrap_or_default(),
+            beatmap_id: data.metadata.beatmap_id.unwrap_or(-1).to_owned(),
+            beatmap_set_id: data.metadata.beatmap_set_id.unwrap_or(-1).to_owned(),
+        },
+        difficulty: BeatmapDifficulty {
+            hp: warnings.required(data.difficulty.hp, Difficulty, "HPDrainRate", 5.0)?,
+            cs: warnings.required(data.difficulty.cs, Difficulty, "CircleSize", 5.0)?,
+            od,
+            ar: data.difficulty.ar.unwrap_or(od),
+            slider_multiplier: warnings.required(
+                data.difficulty.slider_multiplier,
+                Difficulty,
+                "SliderMultiplier",
+                1.4,
+            )?,
+            slider_tick_rate: warnings.required(
+                data.difficulty.slider_tick_rate,
+                Difficulty,
+                "SliderTickRate",
+                1.0,
+            )?,
+        },
+        editor: {
+            let defaults = BeatmapEditor::default();
+            BeatmapEditor {
+                bookmarks: if data.editor.bookmarks.is_empty() {
+                    data.general.editor_bookmarks.unwrap_or_default()
+                } else {
+                    data.editor.bookmarks
                 },
-            ]
-        } else {
-            data.colors
+                distance_spacing: data
+                    .editor
+                    .distance_spacing
+                    .or(data.general.editor_distance_spacing)
+                    .unwrap_or(defaults.distance_spacing),
+                beat_divisor: data.editor.beat_divisor.unwrap_or(defaults.beat_divisor),
+                grid_size: data.editor.grid_size.unwrap_or(defaults.grid_size),
+                timeline_zoom: data.editor.timeline_zoom.unwrap_or(defaults.timeline_zoom),
+            }
         },
+        events: data.events,
+        storyboard: data.storyboard,
+        colors: data
+            .colors
+            .combo_colors
+            .into_iter()
+            .map(|(_, color)| color)
+            .collect(),
+        slider_track_override: data.colors.slider_track_override,
+        slider_border: data.colors.slider_border,
         timing_points: data.timing_points,
         hit_objects: data.hit_objects,
     })
 }
 
+// The line has to be a `Cow` to know whether parts of it can be borrowed.
+#[allow(clippy::ptr_arg)]
 fn read_general_line<'a>(
     general: &mut InParseGeneral<'a>,
-    line: &'a str,
-) -> Result<(), OsuParserError> {
-    let (key, value) = split_key_value(line).ok_or(OsuParserError::BadFormat)?;
-    match key {
-        "AudioFilename" => general.audio_file_name = Some(value),
-        "AudioLeadIn" => general.audio_lead_in = Some(value.parse()?),
+    line: &Cow<'a, str>,
+) -> Result<(), LineError> {
+    let kv = KeyValue::new(line)?;
+    match kv.key {
+        "AudioFilename" => general.audio_file_name = Some(slice_of(line, kv.value)),
+        "AudioLeadIn" => general.audio_lead_in = Some(kv.parse("audio lead-in in milliseconds")?),
         "PreviewTime" => {
-            general.preview_time = if value == "-1" {
+            general.preview_time = if kv.value == "-1" {
                 None
             } else {
-                Some(value.parse()?)
+                Some(kv.parse("preview time in milliseconds")?)
             }
         }
         "Countdown" => {
-            general.countdown = match value.parse::<u8>()? {
+            const EXPECTED: &str = "countdown type (0-3)";
+            general.countdown = Some(match kv.parse::<u8>(EXPECTED)? {
                 0 => None,
                 1 => Some(Countdown::Normal),
                 2 => Some(Countdown::Half),
                 3 => Some(Countdown::Double),
-                _ => return Err(OsuParserError::BadFormat),
-            }
+                _ => return Err(kv.error(OsuParserError::BadFormat, EXPECTED)),
+            })
         }
         "SampleSet" => {
-            general.sample_set = if value == "None" {
+            general.sample_set = if kv.value == "None" {
                 None
             } else {
-                Some(SampleSet::from_str(value).ok_or(OsuParserError::BadFormat)?)
+                Some(SampleSet::from_str(kv.value).ok_or_else(|| {
+                    kv.error(OsuParserError::BadFormat, "Normal, Soft, Drum or None")
+                })?)
             };
         }
-        "StackLeniency" => general.stack_leniency = Some(value.parse()?),
+        "StackLeniency" => general.stack_leniency = Some(kv.parse("stack leniency")?),
         "Mode" => {
-            general.mode = Some(match value.parse::<u8>()? {
+            const EXPECTED: &str = "game mode (0-3)";
+            general.mode = Some(match kv.parse::<u8>(EXPECTED)? {
                 0 => Mode::Osu,
                 1 => Mode::Taiko,
                 2 => Mode::Catch,
                 3 => Mode::Mania,
-                _ => return Err(OsuParserError::BadFormat),
+                _ => return Err(kv.error(OsuParserError::BadFormat, EXPECTED)),
             })
         }
-        "LetterboxInBreaks" => general.letterbox_in_breaks = Some(value.parse::<u8>()? != 0),
-        "WidescreenStoryboard" => general.widescreen_storyboard = Some(value.parse::<u8>()? != 0),
-        "AudioHash" => { /* Ignore */ }
-        _ => return Err(OsuParserError::InvalidKey(key.to_owned())),
+        "LetterboxInBreaks" => general.letterbox_in_breaks = Some(parse_flag(&kv)?),
+        "StoryFireInFront" => general.story_fire_in_front = Some(parse_flag(&kv)?),
+        "UseSkinSprites" => general.use_skin_sprites = Some(parse_flag(&kv)?),
+        "AlwaysShowPlayfield" => general.always_show_playfield = Some(parse_flag(&kv)?),
+        "OverlayPosition" => {
+            general.overlay_position =
+                Some(OverlayPosition::from_str(kv.value).ok_or_else(|| {
+                    kv.error(OsuParserError::BadFormat, "NoChange, Below or Above")
+                })?)
+        }
+        "SkinPreference" => {
+            general.skin_preference = Some(slice_of(line, kv.value)).filter(|skin| !skin.is_empty())
+        }
+        "EpilepsyWarning" => general.epilepsy_warning = Some(parse_flag(&kv)?),
+        "CountdownOffset" => {
+            general.countdown_offset = Some(kv.parse("countdown offset in beats")?)
+        }
+        "SpecialStyle" => general.special_style = Some(parse_flag(&kv)?),
+        "WidescreenStoryboard" => general.widescreen_storyboard = Some(parse_flag(&kv)?),
+        "SamplesMatchPlaybackRate" => general.samples_match_playback_rate = Some(parse_flag(&kv)?),
+        "AudioHash" => general.audio_hash = Some(slice_of(line, kv.value)),
+        "EditorBookmarks" => general.editor_bookmarks = Some(parse_bookmarks(&kv)?),
+        "EditorDistanceSpacing" => {
+            general.editor_distance_spacing = Some(kv.parse("distance spacing")?)
+        }
+        _ => return Err(kv.invalid_key()),
     }
 
     Ok(())
 }
 
-fn read_editor_line(editor: &mut InParseEditor, line: &str) -> Result<(), OsuParserError> {
-    let (key, value) = split_key_value(line).ok_or(OsuParserError::BadFormat)?;
-    match key {
-        "Bookmarks" => {
-            editor.bookmarks = value
-                .split(',')
-                .map(|s| s.parse())
-                .collect::<Result<_, _>>()?
-        }
-        "DistanceSpacing" => editor.distance_spacing = Some(value.parse()?),
-        "BeatDivisor" => editor.beat_divisor = Some(value.parse()?),
-        "GridSize" => editor.grid_size = Some(value.parse()?),
-        "TimelineZoom" => editor.timeline_zoom = Some(value.parse()?),
-        _ => return Err(OsuParserError::InvalidKey(key.to_owned())),
+fn parse_flag(kv: &KeyValue) -> Result<bool, LineError> {
+    Ok(kv.parse::<u8>("0 or 1")? != 0)
+}
+
+fn parse_bookmarks(kv: &KeyValue) -> Result<Vec<Time>, LineError> {
+    kv.value
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|e| kv.error(e, "comma separated times in milliseconds"))
+}
+
+fn read_editor_line(editor: &mut InParseEditor, line: &str) -> Result<(), LineError> {
+    let kv = KeyValue::new(line)?;
+    match kv.key {
+        "Bookmarks" => editor.bookmarks = parse_bookmarks(&kv)?,
+        "DistanceSpacing" => editor.distance_spacing = Some(kv.parse("distance spacing")?),
+        "BeatDivisor" => editor.beat_divisor = Some(kv.parse("beat divisor")?),
+        "GridSize" => editor.grid_size = Some(kv.parse("grid size")?),
+        "TimelineZoom" => editor.timeline_zoom = Some(kv.parse("timeline zoom")?),
+        _ => return Err(kv.invalid_key()),
     }
     Ok(())
 }
 
+#[allow(clippy::ptr_arg)]
 fn read_metadata_line<'a>(
     metadata: &mut InParseMetadata<'a>,
-    line: &'a str,
-) -> Result<(), OsuParserError> {
-    let (key, value) = split_key_value(line).ok_or(OsuParserError::BadFormat)?;
-    match key {
-        "Title" => metadata.title = Some(value),
-        "TitleUnicode" => metadata.title_unicode = Some(value),
-        "Artist" => metadata.artist = Some(value),
-        "ArtistUnicode" => metadata.artist_unicode = Some(value),
-        "Creator" => metadata.creator = Some(value),
-        "Version" => metadata.version = Some(value),
-        "Source" => metadata.source = Some(value),
-        "Tags" => metadata.tags = Some(value),
-        "BeatmapID" => metadata.beatmap_id = Some(value.parse()?),
-        "BeatmapSetID" => metadata.beatmap_set_id = Some(value.parse()?),
-        _ => return Err(OsuParserError::InvalidKey(key.to_owned())),
+    line: &Cow<'a, str>,
+) -> Result<(), LineError> {
+    let kv = KeyValue::new(line)?;
+    match kv.key {
+        "Title" => metadata.title = Some(slice_of(line, kv.value)),
+        "TitleUnicode" => metadata.title_unicode = Some(slice_of(line, kv.value)),
+        "Artist" => metadata.artist = Some(slice_of(line, kv.value)),
+        "ArtistUnicode" => metadata.artist_unicode = Some(slice_of(line, kv.value)),
+        "Creator" => metadata.creator = Some(slice_of(line, kv.value)),
+        "Version" => metadata.version = Some(slice_of(line, kv.value)),
+        "Source" => metadata.source = Some(slice_of(line, kv.value)),
+        "Tags" => metadata.tags = Some(slice_of(line, kv.value)),
+        "BeatmapID" => metadata.beatmap_id = Some(kv.parse("beatmap id")?),
+        "BeatmapSetID" => metadata.beatmap_set_id = Some(kv.parse("beatmap set id")?),
+        _ => return Err(kv.invalid_key()),
     }
     Ok(())
 }
 
-fn read_difficulty_line(
-    difficulty: &mut InParseDifficulty,
-    line: &str,
-) -> Result<(), OsuParserError> {
-    let (key, value) = split_key_value(line).ok_or(OsuParserError::BadFormat)?;
-    match key {
-        "HPDrainRate" => difficulty.hp = Some(value.parse()?),
-        "CircleSize" => difficulty.cs = Some(value.parse()?),
-        "OverallDifficulty" => difficulty.od = Some(value.parse()?),
-        "ApproachRate" => difficulty.ar = Some(value.parse()?),
-        "SliderMultiplier" => difficulty.slider_multiplier = Some(value.parse()?),
-        "SliderTickRate" => difficulty.slider_tick_rate = Some(value.parse()?),
-        _ => return Err(OsuParserError::InvalidKey(key.to_owned())),
+fn read_difficulty_line(difficulty: &mut InParseDifficulty, line: &str) -> Result<(), LineError> {
+    let kv = KeyValue::new(line)?;
+    match kv.key {
+        "HPDrainRate" => difficulty.hp = Some(kv.parse("HP drain rate")?),
+        "CircleSize" => difficulty.cs = Some(kv.parse("circle size")?),
+        "OverallDifficulty" => difficulty.od = Some(kv.parse("overall difficulty")?),
+        "ApproachRate" => difficulty.ar = Some(kv.parse("approach rate")?),
+        "SliderMultiplier" => difficulty.slider_multiplier = Some(kv.parse("slider multiplier")?),
+        "SliderTickRate" => difficulty.slider_tick_rate = Some(kv.parse("slider tick rate")?),
+        _ => return Err(kv.invalid_key()),
     }
     Ok(())
 }
 
-fn read_event_line(line: &str) -> Result<Event, OsuParserError> {
-    let mut entries = line.split(',');
-    let event_type = entries.next().ok_or(OsuParserError::BadFormat)?;
+fn parse_color(kv: &KeyValue) -> Result<RGB<u8>, LineError> {
+    const EXPECTED: &str = "colour (r,g,b)";
+    let mut split = kv.value.split(',').map(|v| v.trim());
+    let mut component = || -> Result<u8, LineError> {
+        split
+            .next()
+            .ok_or_else(|| kv.error(OsuParserError::BadFormat, EXPECTED))?
+            .parse()
+            .map_err(|e| kv.error(e, EXPECTED))
+    };
+    Ok(RGB {
+        r: component()?,
+        g: component()?,
+        b: component()?,
+    })
+}
+
+fn read_colors_line(colors: &mut InParseColors, line: &str) -> Result<(), LineError> {
+    let kv = KeyValue::new(line)?;
+    match kv.key {
+        "SliderTrackOverride" => colors.slider_track_override = Some(parse_color(&kv)?),
+        "SliderBorder" => colors.slider_border = Some(parse_color(&kv)?),
+        _ => {
+            let index: u32 = kv
+                .key
+                .strip_prefix("Combo")
+                .and_then(|index| index.parse().ok())
+                .ok_or_else(|| kv.invalid_key())?;
+            colors.combo_colors.push((index, parse_color(&kv)?));
+        }
+    }
+    Ok(())
+}
+
+/// Removes the quotes around a filename, if there are any.
+fn strip_quotes(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn read_event_line<'a>(
+    events: &mut Vec<EventRef<'a>>,
+    storyboard: &mut StoryboardRef<'a>,
+    line: &Cow<'a, str>,
+) -> Result<(), LineError> {
+    let mut fields = Fields::new(line, ',');
+    let event_type = fields.next("event type")?;
     let event = match event_type {
         "0" => {
             // this is always 0 for some reason
-            entries.next();
-            let filename = entries
-                .next()
-                .ok_or(OsuParserError::BadFormat)?
-                .strip_prefix("\"")
-                .unwrap()
-                .strip_suffix("\"")
-                .unwrap();
-            let x_offset = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-            let y_offset = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-            Event::Background {
-                filename: filename.to_owned(),
-                offset: (x_offset, y_offset),
+            fields.next_opt();
+            // The client also accepts filenames without quotes.
+            let filename = strip_quotes(fields.next("background filename")?);
+            let x_offset = fields.parse_opt("x offset")?.unwrap_or(0);
+            let y_offset = fields.parse_opt("y offset")?.unwrap_or(0);
+            EventRef::Background {
+                filename: slice_of(line, filename),
+                offset: OsuPoint {
+                    x: x_offset,
+                    y: y_offset,
+                },
             }
         }
         "1" | "Video" => {
-            let start_time = entries.next().ok_or(OsuParserError::BadFormat)?;
-            let filename = entries.next().ok_or(OsuParserError::BadFormat)?;
-            let x_offset = entries.next().ok_or(OsuParserError::BadFormat)?;
-            let y_offset = entries.next().ok_or(OsuParserError::BadFormat)?;
-            Event::Video {
-                start_time: start_time.parse()?,
-                filename: filename.to_owned(),
-                offset: (x_offset.parse()?, y_offset.parse()?),
+            let start_time = fields.parse("start time")?;
+            let filename = strip_quotes(fields.next("video filename")?);
+            let x_offset = fields.parse_opt("x offset")?.unwrap_or(0);
+            let y_offset = fields.parse_opt("y offset")?.unwrap_or(0);
+            EventRef::Video {
+                start_time,
+                filename: slice_of(line, filename),
+                offset: OsuPoint {
+                    x: x_offset,
+                    y: y_offset,
+                },
             }
         }
         "2" | "Break" => {
-            let start_time: u32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-            let end_time: u32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-            Event::Break(start_time..end_time)
+            let start_time: Time = fields.parse("start time")?;
+            let end_time: Time = fields.parse("end time")?;
+            EventRef::Break(start_time..end_time)
         }
-        "3" => {
-            println!("Ignoring storyboard...");
-            Event::Break(0..0)
+        "3" | "Colour" => {
+            let start_time = fields.parse("start time")?;
+            let r = fields.parse("red component")?;
+            let g = fields.parse("green component")?;
+            let b = fields.parse("blue component")?;
+            EventRef::Color {
+                start_time,
+                color: RGB { r, g, b },
+            }
         }
-        _ => return Err(OsuParserError::BadFormat),
+        _ => return read_storyboard_line(storyboard, line),
     };
-    Ok(event)
-}
-
-fn read_timing_point_line(line: &str) -> Result<TimingPoint, OsuParserError> {
-    let mut entries = line.split(',');
-    let time: f32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let beat_length: f32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let meter: u8 = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-    let sample_set: u32 = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-    let sample_index: u8 = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-    let volume: u8 = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
-    let uninherited: bool = entries
-        .next()
-        .map(|v| v.parse::<u32>())
-        .transpose()?
-        .unwrap_or(0)
-        != 0;
-    let effects: u8 = entries.next().map(|v| v.parse()).transpose()?.unwrap_or(0);
+    events.push(event);
+    Ok(())
+}
+
+fn read_timing_point_line(line: &str) -> Result<TimingPoint, LineError> {
+    let mut fields = Fields::new(line, ',');
+    let time: Time = fields.parse("time")?;
+    let beat_length: f32 = fields.parse("beat length")?;
+    let meter: u8 = fields.parse_opt("meter")?.unwrap_or(0);
+    const SAMPLE_SET: &str = "sample set (0-3)";
+    let sample_set = match fields.next_opt() {
+        Some(set) => parse_sample_set(set).map_err(|e| fields.error(e, SAMPLE_SET))?,
+        None => None,
+    };
+    let sample_index: u8 = fields.parse_opt("sample index")?.unwrap_or(0);
+    let volume: u8 = fields.parse_opt("volume")?.unwrap_or(0);
+    // Old versions don't have this field, and only knew uninherited timing points.
+    let uninherited: bool = fields.parse_opt::<u32>("0 or 1")?.unwrap_or(1) != 0;
+    let effects = Effects::from_bits_retain(fields.parse_opt("effects")?.unwrap_or(0));
     Ok(TimingPoint {
-        time: time as i32,
+        time,
         beat_length,
         meter,
-        sample_set: match sample_set {
-            0 => None,
-            1 => Some(SampleSet::Normal),
-            2 => Some(SampleSet::Soft),
-            3 => Some(SampleSet::Drum),
-            _ => return Err(OsuParserError::BadFormat),
-        },
+        sample_set,
         sample_index,
         volume,
         uninherited,
@@ -427,42 +829,65 @@ fn read_timing_point_line(line: &str) -> Result<TimingPoint, OsuParserError> {
     })
 }
 
-fn parse_hit_sample(part: &str) -> Result<HitSample, OsuParserError> {
+/// Reads the id of a sample set, 0 means that no set is chosen.
+fn parse_sample_set(s: &str) -> Result<Option<SampleSet>, OsuParserError> {
+    match s.parse::<u8>()? {
+        0 => Ok(None),
+        1 => Ok(Some(SampleSet::Normal)),
+        2 => Ok(Some(SampleSet::Soft)),
+        3 => Ok(Some(SampleSet::Drum)),
+        _ => Err(OsuParserError::BadFormat),
+    }
+}
+
+const HIT_SAMPLE: &str = "hit sample (normalSet:additionSet:index:volume:filename)";
+
+/// `part` has to be a slice of `line`, the file name is borrowed from it.
+fn parse_hit_sample<'a>(
+    line: &Cow<'a, str>,
+    part: &str,
+) -> Result<HitSampleRef<'a>, OsuParserError> {
     let mut split = part.split(':');
-    let normal_set = split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let addition_set = split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
+    let normal_set = parse_sample_set(split.next().ok_or(OsuParserError::BadFormat)?)?;
+    let addition_set = parse_sample_set(split.next().ok_or(OsuParserError::BadFormat)?)?;
     let index = split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
     let volume = split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let filename = split.next().unwrap_or("").to_owned();
-    Ok(HitSample {
+    // Most objects don't have a custom sample, so don't allocate for them.
+    let filename = split
+        .next()
+        .filter(|filename| !filename.is_empty())
+        .map(|filename| slice_of(line, filename));
+    Ok(HitSampleRef {
         normal_set,
         addition_set,
         index,
         volume,
-        filename: Some(filename),
+        filename,
     })
 }
 
-fn read_hitobject_line(line: &str) -> Result<HitObject, OsuParserError> {
-    let mut entries = line.split(',').filter(|&s| !s.is_empty());
-    let x: u16 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let y: u16 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let time: f32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let ty: u32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-    let hit_sound: u8 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
+#[allow(clippy::ptr_arg)]
+fn read_hitobject_line<'a>(line: &Cow<'a, str>) -> Result<HitObjectRef<'a>, LineError> {
+    let mut fields = Fields::new(line, ',').skip_empty();
+    let x: i16 = fields.parse("x position")?;
+    let y: i16 = fields.parse("y position")?;
+    let time: Time = fields.parse("time")?;
+    let ty = HitObjectType::from_bits_retain(fields.parse("object type")?);
+    let hit_sound = HitSound::from_bits_retain(fields.parse("hit sound")?);
     let mut hit_sample = None;
-    let specific = if ty & (1 << 0) > 0 {
+    let specific = if ty.contains(HitObjectType::CIRCLE) {
         // Hit Circle. Nothing extra
         SpecificHitObject::Circle
-    } else if ty & (1 << 1) > 0 {
+    } else if ty.contains(HitObjectType::SLIDER) {
         // Slider
-        let mut curve_data = entries.next().ok_or(OsuParserError::BadFormat)?.split('|');
-        let curve_type = match curve_data.next().ok_or(OsuParserError::BadFormat)? {
-            "B" => CurveType::Bezier,
-            "C" => CurveType::Catmull,
-            "P" => CurveType::Perfect,
-            "L" => CurveType::Linear,
-            _ => return Err(OsuParserError::BadFormat),
+        const CURVE: &str = "curve (type|x:y|x:y...)";
+        let mut curve_data = fields.next(CURVE)?.split('|');
+        let curve_type = match curve_data.next() {
+            Some("B") => CurveType::Bezier,
+            Some("C") => CurveType::Catmull,
+            Some("P") => CurveType::Perfect,
+            Some("L") => CurveType::Linear,
+            _ => return Err(fields.error(OsuParserError::BadFormat, CURVE)),
         };
 
         let curve_points = curve_data
@@ -472,55 +897,91 @@ fn read_hitobject_line(line: &str) -> Result<HitObject, OsuParserError> {
                 let y = p_split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
                 Ok(OsuPoint { x, y })
             })
-            .collect::<Result<Vec<_>, OsuParserError>>()?;
-        let slides: u8 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-        let length: f32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-        let edge_sounds = entries.next().map(|s| {
-            s.split('|')
-                .map(|e| e.parse::<u8>().unwrap())
-                .collect::<Vec<_>>()
-        });
-        let _edge_sets = entries.next().map(|s| s.split('|').collect::<Vec<_>>());
+            .collect::<Result<Vec<_>, OsuParserError>>()
+            .map_err(|e| fields.error(e, CURVE))?;
+        let slides: u8 = fields.parse("slide count")?;
+        let length: f32 = fields.parse("pixel length")?;
+        let edge_sounds = fields
+            .next_opt()
+            .map(|s| {
+                s.split('|')
+                    .map(|e| e.parse().map(HitSound::from_bits_retain))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| fields.error(e, "edge sounds (sound|sound...)"))
+            })
+            .transpose()?;
+        const EDGE_SETS: &str = "edge sets (normalSet:additionSet|...)";
+        let edge_sets = fields
+            .next_opt()
+            .map(|s| {
+                s.split('|')
+                    .map(|e| {
+                        let (normal_set, addition_set) =
+                            e.split_once(':').ok_or(OsuParserError::BadFormat)?;
+                        Ok(EdgeSet {
+                            normal_set: parse_sample_set(normal_set)?,
+                            addition_set: parse_sample_set(addition_set)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, OsuParserError>>()
+                    .map_err(|e| fields.error(e, EDGE_SETS))
+            })
+            .transpose()?;
+        let edge_sounds = edge_sounds.unwrap_or_else(|| vec![HitSound::empty(); 2]);
         SpecificHitObject::Slider {
             curve_type,
             curve_points,
             slides,
             length,
-            edge_sounds: edge_sounds.unwrap_or_else(|| vec![0, 0]),
+            edge_sets: edge_sets.unwrap_or_else(|| vec![EdgeSet::default(); edge_sounds.len()]),
+            edge_sounds,
         }
-    } else if ty & (1 << 3) > 0 {
+    } else if ty.contains(HitObjectType::SPINNER) {
         // Spinner
-        let end_time: u32 = entries.next().ok_or(OsuParserError::BadFormat)?.parse()?;
+        let end_time: Time = fields.parse("end time")?;
         SpecificHitObject::Spinner { end_time }
-    } else if ty & (1 << 7) > 0 {
+    } else if ty.contains(HitObjectType::MANIA_HOLD) {
         // Mania Hold
-        let mut split = entries.next().ok_or(OsuParserError::BadFormat)?.split(':');
-        let _end_time: u32 = split.next().ok_or(OsuParserError::BadFormat)?.parse()?;
-        let hit_sample_str = split.next().ok_or(OsuParserError::BadFormat)?;
-        hit_sample = Some(parse_hit_sample(hit_sample_str)?);
-        SpecificHitObject::ManiaHold {}
+        const HOLD: &str = "end time and hit sample (endTime:hitSample)";
+        let hold = fields.next(HOLD)?;
+        // Files written without hit samples only have the end time.
+        let (end_time, hit_sample_str) = match hold.split_once(':') {
+            Some((end_time, hit_sample_str)) => (end_time, Some(hit_sample_str)),
+            None => (hold, None),
+        };
+        let end_time: Time = end_time.parse().map_err(|e| fields.error(e, HOLD))?;
+        if let Some(hit_sample_str) = hit_sample_str {
+            hit_sample =
+                Some(parse_hit_sample(line, hit_sample_str).map_err(|e| fields.error(e, HOLD))?);
+        }
+        SpecificHitObject::ManiaHold { end_time }
     } else {
-        return Err(OsuParserError::BadFormat);
+        return Err(LineError {
+            error: OsuParserError::BadFormat,
+            field: Some(3),
+            offset: None,
+            expected: Some("object type with the circle, slider, spinner or hold bit set"),
+        });
     };
-    let new_combo = ty & (1 << 2) > 0;
-    let _color_skip = (ty >> 3) & 0b111;
-    let split = entries.next();
-    if let Some(split) = split {
-        hit_sample = Some(parse_hit_sample(split)?);
+    let new_combo = ty.contains(HitObjectType::NEW_COMBO);
+    let combo_skip = ty.combo_skip();
+    if let Some(split) = fields.next_opt() {
+        hit_sample = Some(parse_hit_sample(line, split).map_err(|e| fields.error(e, HIT_SAMPLE))?);
     }
 
-    Ok(HitObject {
-        position: (x, y),
-        time: time as u32,
+    Ok(HitObjectRef {
+        position: OsuPoint { x, y },
+        time,
         specific,
         hit_sound,
-        hit_sample: hit_sample.unwrap_or(HitSample {
-            normal_set: 0,
-            addition_set: 0,
+        hit_sample: hit_sample.unwrap_or(HitSampleRef {
+            normal_set: None,
+            addition_set: None,
             index: 0,
             volume: 100,
             filename: None,
         }),
         new_combo,
+        combo_skip,
     })
 }
@@ -0,0 +1,163 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek},
+    path::Path,
+};
+
+use osu_types::storyboard::Storyboard;
+use zip::ZipArchive;
+
+use crate::{
+    error::{OsuParserError, OsuParserResult},
+    load_bytes,
+    set::{has_extension, hitsound_index, load_storyboard_bytes, normalize_name},
+    Beatmap, BeatmapParseOptions,
+};
+
+/// A beatmapset archive (.osz), which is a plain zip file.
+///
+/// Files are looked up the way the client does it on Windows: case-insensitive, and with `\` and
+/// `/` both accepted as separators.
+pub struct OszArchive<R> {
+    archive: ZipArchive<R>,
+    /// File names in archive order with their index in the zip, without directories.
+    files: Vec<(String, usize)>,
+}
+
+impl OszArchive<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> OsuParserResult<Self> {
+        let file = File::open(path).map_err(OsuParserError::from)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> OszArchive<R> {
+    pub fn new(reader: R) -> OsuParserResult<Self> {
+        let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+        let mut files = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i).map_err(zip_error)?;
+            if file.is_file() {
+                files.push((file.name().to_owned(), i));
+            }
+        }
+        Ok(Self { archive, files })
+    }
+
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Names of the difficulties (.osu).
+    pub fn beatmap_file_names(&self) -> impl Iterator<Item = &str> {
+        self.file_names()
+            .filter(|name| has_extension(name, &["osu"]))
+    }
+
+    /// Names of the storyboards (.osb).
+    pub fn storyboard_file_names(&self) -> impl Iterator<Item = &str> {
+        self.file_names()
+            .filter(|name| has_extension(name, &["osb"]))
+    }
+
+    /// Names of the custom hit sounds, like `soft-hitclap2.wav`, that replace the skin's samples.
+    pub fn hitsound_file_names(&self) -> impl Iterator<Item = &str> {
+        self.file_names()
+            .filter(|name| hitsound_index(name).is_some())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    /// Opens a file for reading without extracting the archive.
+    pub fn open_file(&mut self, name: &str) -> OsuParserResult<impl Read + '_> {
+        let index = self.find(name).ok_or_else(|| not_found(name))?;
+        Ok(self.archive.by_index(index).map_err(zip_error)?)
+    }
+
+    pub fn read_file(&mut self, name: &str) -> OsuParserResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.open_file(name)?
+            .read_to_end(&mut bytes)
+            .map_err(OsuParserError::from)?;
+        Ok(bytes)
+    }
+
+    pub fn load_beatmap(
+        &mut self,
+        name: &str,
+        options: BeatmapParseOptions,
+    ) -> OsuParserResult<Beatmap> {
+        let bytes = self.read_file(name)?;
+        load_bytes(&bytes, options).map(|(beatmap, _)| beatmap)
+    }
+
+    /// Parses every difficulty in the archive. A difficulty that fails to parse doesn't stop the
+    /// others from being read.
+    pub fn load_beatmaps(
+        &mut self,
+        options: BeatmapParseOptions,
+    ) -> Vec<(String, OsuParserResult<Beatmap>)> {
+        let names: Vec<String> = self.beatmap_file_names().map(str::to_owned).collect();
+        names
+            .into_iter()
+            .map(|name| {
+                let beatmap = self.load_beatmap(&name, options);
+                (name, beatmap)
+            })
+            .collect()
+    }
+
+    pub fn load_storyboard(&mut self, name: &str) -> OsuParserResult<Storyboard> {
+        let bytes = self.read_file(name)?;
+        load_storyboard_bytes(&bytes)
+    }
+
+    /// Parses every storyboard in the archive. There is usually at most one.
+    pub fn load_storyboards(&mut self) -> Vec<(String, OsuParserResult<Storyboard>)> {
+        let names: Vec<String> = self.storyboard_file_names().map(str::to_owned).collect();
+        names
+            .into_iter()
+            .map(|name| {
+                let storyboard = self.load_storyboard(&name);
+                (name, storyboard)
+            })
+            .collect()
+    }
+
+    /// Opens the song of a difficulty.
+    pub fn open_audio(&mut self, beatmap: &Beatmap) -> OsuParserResult<impl Read + '_> {
+        self.open_file(&beatmap.info.general_data.audio_file_name)
+    }
+
+    /// Opens the background image of a difficulty. Returns `None` if it doesn't have one.
+    pub fn open_background(
+        &mut self,
+        beatmap: &Beatmap,
+    ) -> OsuParserResult<Option<impl Read + '_>> {
+        match beatmap.background_file_name() {
+            Some(name) => self.open_file(name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        let name = normalize_name(name);
+        self.files
+            .iter()
+            .find(|(candidate, _)| normalize_name(candidate) == name)
+            .map(|&(_, index)| index)
+    }
+}
+
+fn zip_error(error: zip::result::ZipError) -> OsuParserError {
+    OsuParserError::IOError(error.into())
+}
+
+fn not_found(name: &str) -> OsuParserError {
+    OsuParserError::IOError(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("'{}' is not in the archive", name),
+    ))
+}
@@ -0,0 +1,329 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use osu_types::{
+    osz2::BeatmapPackage,
+    storyboard::{Storyboard, StoryboardObject},
+};
+
+use crate::{
+    error::{OsuParserError, OsuParserResult, ParseError},
+    load_bytes, load_storyboard_content, Beatmap, BeatmapParseOptions,
+};
+
+/// All difficulties of a beatmapset together with the storyboard and the other files they share.
+#[derive(Debug)]
+pub struct BeatmapSet {
+    /// Every file of the set, relative to its root with `/` as separator.
+    pub files: Vec<String>,
+    pub difficulties: Vec<SetDifficulty>,
+    /// The .osb file, if the set has one. Only the first one that can be read is used, any
+    /// others are listed by [`BeatmapSet::unused_files`].
+    pub storyboard_file_name: Option<String>,
+    /// Storyboard shared by all difficulties. Empty if there is no .osb file.
+    pub storyboard: Storyboard,
+    /// .osu and .osb files that could not be read.
+    pub errors: Vec<(String, ParseError)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetDifficulty {
+    pub file_name: String,
+    pub beatmap: Beatmap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Audio,
+    Background,
+    Video,
+    /// A custom sample set on a hit object.
+    HitSound,
+    StoryboardImage,
+    StoryboardSample,
+}
+
+/// A file that a difficulty or the storyboard points to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReference {
+    pub kind: AssetKind,
+    /// The name as written in the .osu or .osb file.
+    pub file_name: String,
+    /// The matching file of the set, `None` if it is missing.
+    pub resolved: Option<String>,
+    /// Index of the difficulty in [`BeatmapSet::difficulties`], `None` for the .osb storyboard.
+    pub difficulty: Option<usize>,
+}
+
+impl BeatmapSet {
+    /// Builds a set from a list of files. `read_file` is called with the names of the .osu and
+    /// .osb files to get their content.
+    pub fn from_files<S: Into<String>>(
+        files: impl IntoIterator<Item = S>,
+        mut read_file: impl FnMut(&str) -> OsuParserResult<Vec<u8>>,
+        options: BeatmapParseOptions,
+    ) -> Self {
+        let mut set = BeatmapSet {
+            files: files.into_iter().map(Into::into).collect(),
+            difficulties: Vec::new(),
+            storyboard_file_name: None,
+            storyboard: Storyboard::default(),
+            errors: Vec::new(),
+        };
+
+        for file_name in &set.files {
+            if has_extension(file_name, &["osu"]) {
+                match read_file(file_name).and_then(|bytes| load_bytes(&bytes, options)) {
+                    Ok((beatmap, _)) => set.difficulties.push(SetDifficulty {
+                        file_name: file_name.clone(),
+                        beatmap,
+                    }),
+                    Err(error) => set.errors.push((file_name.clone(), error)),
+                }
+            } else if has_extension(file_name, &["osb"]) && set.storyboard_file_name.is_none() {
+                match read_file(file_name).and_then(|bytes| load_storyboard_bytes(&bytes)) {
+                    Ok(storyboard) => {
+                        set.storyboard_file_name = Some(file_name.clone());
+                        set.storyboard = storyboard;
+                    }
+                    Err(error) => set.errors.push((file_name.clone(), error)),
+                }
+            }
+        }
+        set
+    }
+
+    /// Reads a beatmapset folder, including its subfolders.
+    pub fn from_folder(
+        path: impl AsRef<Path>,
+        options: BeatmapParseOptions,
+    ) -> OsuParserResult<Self> {
+        let root = path.as_ref();
+        let mut files = Vec::new();
+        list_files(root, PathBuf::new(), &mut files).map_err(OsuParserError::from)?;
+        files.sort();
+        Ok(Self::from_files(
+            files,
+            |name| Ok(std::fs::read(root.join(name)).map_err(OsuParserError::from)?),
+            options,
+        ))
+    }
+
+    /// Reads a beatmapset from an .osz archive.
+    #[cfg(feature = "osz")]
+    pub fn from_osz<R: io::Read + io::Seek>(
+        archive: &mut crate::OszArchive<R>,
+        options: BeatmapParseOptions,
+    ) -> Self {
+        let files: Vec<String> = archive.file_names().map(str::to_owned).collect();
+        Self::from_files(files, |name| archive.read_file(name), options)
+    }
+
+    /// Builds a set from the file table of an .osz2 package. The package only describes the files,
+    /// so `read_file` has to supply the content of the .osu and .osb files.
+    pub fn from_package(
+        package: &BeatmapPackage,
+        mut read_file: impl FnMut(&str) -> io::Result<Vec<u8>>,
+        options: BeatmapParseOptions,
+    ) -> Self {
+        let mut files: Vec<&String> = package.files.keys().collect();
+        files.sort();
+        Self::from_files(
+            files.into_iter().cloned(),
+            |name| Ok(read_file(name).map_err(OsuParserError::from)?),
+            options,
+        )
+    }
+
+    /// Finds a file of the set the way the client does: case-insensitive, with `\` and `/` both
+    /// accepted as separators.
+    pub fn resolve(&self, file_name: &str) -> Option<&str> {
+        let wanted = normalize_name(file_name);
+        self.files
+            .iter()
+            .find(|name| normalize_name(name) == wanted)
+            .map(String::as_str)
+    }
+
+    /// Every file that the difficulties and storyboards point to, in the order they are
+    /// referenced. A file that is used several times is listed once per difficulty.
+    pub fn file_references(&self) -> Vec<FileReference> {
+        let mut references = Vec::new();
+        let mut seen = HashSet::new();
+        let mut add = |kind, file_name: &str, difficulty| {
+            if file_name.is_empty() || !seen.insert((kind, normalize_name(file_name), difficulty)) {
+                return;
+            }
+            references.push(FileReference {
+                kind,
+                file_name: file_name.to_owned(),
+                resolved: self.resolve(file_name).map(str::to_owned),
+                difficulty,
+            });
+        };
+
+        for (i, difficulty) in self.difficulties.iter().enumerate() {
+            let beatmap = &difficulty.beatmap;
+            add(
+                AssetKind::Audio,
+                &beatmap.info.general_data.audio_file_name,
+                Some(i),
+            );
+            if let Some(background) = beatmap.background_file_name() {
+                add(AssetKind::Background, background, Some(i));
+            }
+            if let Some(video) = beatmap.video_file_name() {
+                add(AssetKind::Video, video, Some(i));
+            }
+            for object in &beatmap.hit_objects {
+                if let Some(filename) = &object.hit_sample.filename {
+                    add(AssetKind::HitSound, filename, Some(i));
+                }
+            }
+            for (kind, file_name) in storyboard_files(&beatmap.storyboard) {
+                add(kind, &file_name, Some(i));
+            }
+        }
+        for (kind, file_name) in storyboard_files(&self.storyboard) {
+            add(kind, &file_name, None);
+        }
+        references
+    }
+
+    /// References that don't match any file of the set.
+    pub fn missing_files(&self) -> Vec<FileReference> {
+        self.file_references()
+            .into_iter()
+            .filter(|reference| reference.resolved.is_none())
+            .collect()
+    }
+
+    /// Files that nothing in the set uses. Custom hit sounds like `soft-hitclap2.wav` count as
+    /// used if a difficulty uses their sample index, and .osb files other than
+    /// [`BeatmapSet::storyboard_file_name`] are unused.
+    pub fn unused_files(&self) -> Vec<&str> {
+        let used: HashSet<String> = self
+            .file_references()
+            .into_iter()
+            .filter_map(|reference| reference.resolved)
+            .collect();
+        let sample_indices: HashSet<u8> = self
+            .difficulties
+            .iter()
+            .flat_map(|difficulty| {
+                let beatmap = &difficulty.beatmap;
+                let timing_points = beatmap.timing_points.iter().map(|tp| tp.sample_index);
+                let objects = beatmap.hit_objects.iter().map(|o| o.hit_sample.index);
+                timing_points.chain(objects)
+            })
+            .collect();
+
+        self.files
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !has_extension(name, &["osu"]))
+            .filter(|name| Some(*name) != self.storyboard_file_name.as_deref())
+            .filter(|name| !used.contains(*name))
+            .filter(|name| !hitsound_index(name).is_some_and(|i| sample_indices.contains(&i)))
+            .collect()
+    }
+}
+
+/// Files used by a storyboard. Animations use one file per frame.
+fn storyboard_files(storyboard: &Storyboard) -> Vec<(AssetKind, String)> {
+    let mut files = Vec::new();
+    for object in &storyboard.objects {
+        match object {
+            StoryboardObject::Sprite(sprite) => {
+                files.push((AssetKind::StoryboardImage, sprite.filename.clone()))
+            }
+            StoryboardObject::Animation(animation) => {
+                let (stem, extension) = match animation.filename.rsplit_once('.') {
+                    Some((stem, extension)) => (stem, format!(".{}", extension)),
+                    None => (animation.filename.as_str(), String::new()),
+                };
+                for frame in 0..animation.frame_count {
+                    files.push((
+                        AssetKind::StoryboardImage,
+                        format!("{}{}{}", stem, frame, extension),
+                    ));
+                }
+            }
+            StoryboardObject::Sample(sample) => {
+                files.push((AssetKind::StoryboardSample, sample.filename.clone()))
+            }
+        }
+    }
+    files
+}
+
+fn list_files(root: &Path, folder: PathBuf, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in std::fs::read_dir(root.join(&folder))? {
+        let entry = entry?;
+        let path = folder.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_files(root, path, files)?;
+        } else {
+            let name = path.to_string_lossy().replace('\\', "/");
+            files.push(name);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn load_storyboard_bytes(bytes: &[u8]) -> OsuParserResult<Storyboard> {
+    let content = std::str::from_utf8(bytes)
+        .map_err(|e| OsuParserError::from(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    load_storyboard_content(content)
+}
+
+/// Lowercase with `/` as separator, so that file references from beatmaps match the files.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.trim().replace('\\', "/").to_lowercase()
+}
+
+pub(crate) fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    match name.rsplit_once('.') {
+        Some((_, extension)) => extensions
+            .iter()
+            .any(|candidate| extension.eq_ignore_ascii_case(candidate)),
+        None => false,
+    }
+}
+
+/// The sample index of a file that replaces one of the skin's hit sounds, like
+/// `drum-hitwhistle.wav` (index 1) or `normal-sliderslide3.ogg` (index 3).
+pub(crate) fn hitsound_index(name: &str) -> Option<u8> {
+    const SAMPLE_SETS: [&str; 3] = ["normal-", "soft-", "drum-"];
+    const SAMPLES: [&str; 7] = [
+        "hitnormal",
+        "hitwhistle",
+        "hitfinish",
+        "hitclap",
+        "slidertick",
+        "sliderslide",
+        "sliderwhistle",
+    ];
+
+    if name.contains(['/', '\\']) || !has_extension(name, &["wav", "ogg", "mp3"]) {
+        return None;
+    }
+    let name = name.to_lowercase();
+    let stem = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(stem, _)| stem);
+    let sample = SAMPLE_SETS.iter().find_map(|set| stem.strip_prefix(set))?;
+    let index = sample.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let sample = &sample[..sample.len() - index.len()];
+    if !SAMPLES.contains(&sample) {
+        return None;
+    }
+    if index.is_empty() {
+        Some(1)
+    } else {
+        index.parse().ok()
+    }
+}
@@ -0,0 +1,23 @@
+use osu_utils::{StackingAlgorithm, TimingIndex};
+
+use crate::Beatmap;
+
+impl Beatmap {
+    /// The stacking algorithm the client uses for the beatmap's [`Beatmap::format_version`].
+    pub fn stacking_algorithm(&self) -> StackingAlgorithm {
+        StackingAlgorithm::for_format_version(self.format_version)
+    }
+
+    /// Stack height of every hit object, in the same order as [`Beatmap::hit_objects`]. See
+    /// [`osu_utils::stack_heights`], the objects have to be sorted by time.
+    pub fn stack_heights(&self) -> Vec<i32> {
+        let timing = TimingIndex::new(&self.timing_points);
+        osu_utils::stack_heights(
+            &self.hit_objects,
+            &self.info.difficulty,
+            &timing,
+            self.info.general_data.stack_leniency,
+            self.stacking_algorithm(),
+        )
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use osu_types::{Event, Mode, SpecificHitObject, Time};
+use osu_utils::{Slider, TimingIndex};
+
+use crate::Beatmap;
+
+/// Numbers shown for a beatmap in song select and on the website.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BeatmapStatistics {
+    /// Highest reachable combo in the beatmap's own mode.
+    pub max_combo: u32,
+    pub circles: u32,
+    pub sliders: u32,
+    pub spinners: u32,
+    /// osu!mania hold notes.
+    pub holds: u32,
+    /// Milliseconds from the start of the first object to the end of the last one.
+    pub total_length: f64,
+    /// Like `total_length`, but without breaks.
+    pub drain_time: f64,
+    /// BPM of the uninherited timing points. All 0 if there are none.
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+    /// The BPM that is active for the longest time.
+    pub most_common_bpm: f64,
+}
+
+impl Beatmap {
+    /// Computes object counts, lengths and BPMs, and the max combo for [`BeatmapGeneralData::mode`].
+    ///
+    /// Combo is counted like in lazer: sliders give combo for the head, every tick and repeat and
+    /// the tail in osu! and osu!catch, but none in osu!taiko, and osu!mania hold notes give combo
+    /// for the head and the tail.
+    ///
+    /// [`BeatmapGeneralData::mode`]: osu_types::BeatmapGeneralData::mode
+    pub fn statistics(&self) -> BeatmapStatistics {
+        let mode = self.info.general_data.mode;
+        let timing = TimingIndex::new(&self.timing_points);
+        let mut statistics = BeatmapStatistics::default();
+
+        let mut first_time: Option<Time> = None;
+        let mut last_time: Option<Time> = None;
+        for object in &self.hit_objects {
+            let end_time = match &object.specific {
+                SpecificHitObject::Circle => {
+                    statistics.circles += 1;
+                    statistics.max_combo += 1;
+                    object.time
+                }
+                SpecificHitObject::Slider { .. } => {
+                    statistics.sliders += 1;
+                    // A slider that can't be built still counts its head and tail.
+                    let slider = Slider::new(object, &self.info.difficulty, &timing);
+                    let (nested, end_time) = slider.map_or((2, object.time), |slider| {
+                        (slider.nested.len() as u32, slider.end_time)
+                    });
+                    match mode {
+                        Mode::Osu | Mode::Catch => statistics.max_combo += nested,
+                        Mode::Taiko => {}
+                        Mode::Mania => statistics.max_combo += 2,
+                    }
+                    end_time
+                }
+                SpecificHitObject::Spinner { end_time } => {
+                    statistics.spinners += 1;
+                    if mode == Mode::Osu {
+                        statistics.max_combo += 1;
+                    }
+                    *end_time
+                }
+                SpecificHitObject::ManiaHold { end_time } => {
+                    statistics.holds += 1;
+                    statistics.max_combo += 2;
+                    *end_time
+                }
+            };
+            first_time = Some(first_time.map_or(object.time, |time| time.min(object.time)));
+            last_time = Some(last_time.map_or(end_time, |time| time.max(end_time)));
+        }
+
+        if let (Some(first_time), Some(last_time)) = (first_time, last_time) {
+            statistics.total_length = last_time - first_time;
+            let breaks: f64 = self
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Break(range) => Some(range.end - range.start),
+                    _ => None,
+                })
+                .sum();
+            statistics.drain_time = (statistics.total_length - breaks).max(0.0);
+        }
+
+        let red_lines: Vec<_> = timing
+            .points()
+            .iter()
+            .filter(|point| point.uninherited && point.beat_length > 0.0)
+            .collect();
+        if !red_lines.is_empty() {
+            let bpm = |beat_length: f64| 60_000.0 / beat_length;
+            let beat_lengths = red_lines.iter().map(|point| f64::from(point.beat_length));
+            statistics.min_bpm = bpm(beat_lengths.clone().fold(f64::MIN, f64::max));
+            statistics.max_bpm = bpm(beat_lengths.fold(f64::MAX, f64::min));
+
+            // Like the client, the first timing point counts from 0 and the last one until the
+            // end of the last object.
+            let end = last_time.unwrap_or(0.0);
+            // Beat lengths are compared rounded to a thousandth of a millisecond.
+            let mut durations: HashMap<i64, (f64, f64)> = HashMap::new();
+            for (i, point) in red_lines.iter().enumerate() {
+                let start = if i == 0 { 0.0 } else { point.time };
+                let next = red_lines.get(i + 1).map_or(end, |next| next.time);
+                let duration = if point.time > end { 0.0 } else { next - start };
+                let beat_length = f64::from(point.beat_length);
+                let key = (beat_length * 1000.0).round() as i64;
+                durations.entry(key).or_insert((beat_length, 0.0)).1 += duration;
+            }
+            let (beat_length, _) = durations
+                .into_values()
+                .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.total_cmp(&a.0)))
+                .unwrap();
+            statistics.most_common_bpm = bpm(beat_length);
+        }
+
+        statistics
+    }
+}
@@ -0,0 +1,368 @@
+use std::{borrow::Cow, convert::TryFrom};
+
+use osu_types::{
+    storyboard::{Command, Easing, Layer, LoopType, Origin, Parameter, Storyboard, Transform},
+    BeatmapFileSection, Time, RGB,
+};
+
+use crate::{
+    error::{LineError, OsuParserError, OsuParserResult},
+    fields::{slice_of, Fields},
+    read_event_line, read_section_line, strip_quotes, AnimationRef, SampleRef, SpriteRef,
+    StoryboardObjectRef, StoryboardRef,
+};
+
+/// Loads a storyboard file (.osb), which is shared by all difficulties of a beatmapset.
+pub fn load_storyboard_file(path: impl AsRef<std::path::Path>) -> OsuParserResult<Storyboard> {
+    let content = std::fs::read_to_string(path).map_err(OsuParserError::from)?;
+    load_storyboard_content(&content)
+}
+
+pub fn load_storyboard_content(content: &str) -> OsuParserResult<Storyboard> {
+    let lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|&(_, line)| !line.trim().is_empty() && !line.starts_with("//"))
+        // Storyboard files don't need a version, but some have one anyway.
+        .skip_while(|(_, line)| line.starts_with("osu file format v"));
+
+    let mut current_section: Option<BeatmapFileSection> = None;
+    let mut variables = Vec::new();
+    let mut events = Vec::new();
+    let mut storyboard = StoryboardRef::default();
+
+    for (line_number, line) in lines {
+        if line.starts_with('[') && line.ends_with(']') {
+            current_section =
+                Some(read_section_line(line).map_err(|e| e.at(None, line_number, line))?);
+            continue;
+        }
+
+        let section = current_section.ok_or_else(|| {
+            LineError::from(OsuParserError::DataOutsideSection).at(None, line_number, line)
+        })?;
+        match section {
+            BeatmapFileSection::Variables => {
+                read_variable_line(&mut variables, &Cow::Borrowed(line))
+                    .map_err(|e| e.at(Some(section), line_number, line))?;
+            }
+            BeatmapFileSection::Events => {
+                let line = expand_variables(&variables, Cow::Borrowed(line));
+                // Backgrounds, videos and breaks only mean something in a difficulty.
+                read_event_line(&mut events, &mut storyboard, &line)
+                    .map_err(|e| e.at(Some(section), line_number, &line))?;
+            }
+            // Anything else belongs in a .osu file.
+            _ => {}
+        }
+    }
+
+    Ok(storyboard.into_owned())
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn read_variable_line<'a>(
+    variables: &mut Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    line: &Cow<'a, str>,
+) -> Result<(), LineError> {
+    let (name, value) = line
+        .split_once('=')
+        .filter(|(name, _)| name.starts_with('$'))
+        .ok_or(LineError {
+            error: OsuParserError::BadFormat,
+            field: None,
+            offset: Some(0),
+            expected: Some("variable definition ($name=value)"),
+        })?;
+    variables.push((slice_of(line, name), slice_of(line, value)));
+    // Longest names first, so a variable can't replace the start of a longer one.
+    variables.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    Ok(())
+}
+
+/// Replaces every `$name` in the line with the value of the variable.
+pub(crate) fn expand_variables<'a>(
+    variables: &[(Cow<str>, Cow<str>)],
+    line: Cow<'a, str>,
+) -> Cow<'a, str> {
+    if variables.is_empty() || !line.contains('$') {
+        return line;
+    }
+    let mut line = line.into_owned();
+    for (name, value) in variables {
+        line = line.replace(name.as_ref(), value);
+    }
+    Cow::Owned(line)
+}
+
+/// Reads a storyboard object or command line from the `[Events]` section.
+#[allow(clippy::ptr_arg)]
+pub(crate) fn read_storyboard_line<'a>(
+    storyboard: &mut StoryboardRef<'a>,
+    line: &Cow<'a, str>,
+) -> Result<(), LineError> {
+    let depth = line.chars().take_while(|&c| c == ' ' || c == '_').count();
+    if depth == 0 {
+        let object = read_object(line)?;
+        storyboard.objects.push(object);
+        return Ok(());
+    }
+
+    let no_parent = || LineError {
+        error: OsuParserError::BadFormat,
+        field: None,
+        offset: Some(0),
+        expected: Some("a sprite or animation before its commands"),
+    };
+    let mut commands = match storyboard.objects.last_mut() {
+        Some(StoryboardObjectRef::Sprite(sprite)) => &mut sprite.commands,
+        Some(StoryboardObjectRef::Animation(animation)) => &mut animation.commands,
+        _ => return Err(no_parent()),
+    };
+    for _ in 1..depth {
+        commands = match commands.last_mut() {
+            Some(Command::Loop { commands, .. }) | Some(Command::Trigger { commands, .. }) => {
+                commands
+            }
+            _ => {
+                return Err(LineError {
+                    expected: Some("a loop or trigger before the nested commands"),
+                    ..no_parent()
+                })
+            }
+        };
+    }
+    read_command(commands, line)
+}
+
+fn parse_layer(fields: &mut Fields) -> Result<Layer, LineError> {
+    const EXPECTED: &str = "layer (Background, Fail, Pass, Foreground or Overlay)";
+    let s = fields.next(EXPECTED)?;
+    Layer::from_str(s)
+        .or_else(|| Layer::try_from(s.parse::<u8>().ok()?).ok())
+        .ok_or_else(|| fields.error(OsuParserError::BadFormat, EXPECTED))
+}
+
+fn parse_origin(fields: &mut Fields) -> Result<Origin, LineError> {
+    const EXPECTED: &str = "origin";
+    let s = fields.next(EXPECTED)?;
+    Origin::from_str(s)
+        .or_else(|| Origin::try_from(s.parse::<u8>().ok()?).ok())
+        .ok_or_else(|| fields.error(OsuParserError::BadFormat, EXPECTED))
+}
+
+fn parse_loop_type(fields: &mut Fields) -> Result<LoopType, LineError> {
+    const EXPECTED: &str = "LoopForever or LoopOnce";
+    match fields.next_opt() {
+        None => Ok(LoopType::LoopForever),
+        Some(s) => LoopType::from_str(s)
+            .or_else(|| LoopType::try_from(s.parse::<u8>().ok()?).ok())
+            .ok_or_else(|| fields.error(OsuParserError::BadFormat, EXPECTED)),
+    }
+}
+
+#[allow(clippy::ptr_arg)]
+fn read_object<'a>(line: &Cow<'a, str>) -> Result<StoryboardObjectRef<'a>, LineError> {
+    let mut fields = Fields::new(line, ',');
+    let object_type = fields.next("storyboard object type")?;
+    let object = match object_type {
+        "4" | "Sprite" | "6" | "Animation" => {
+            let layer = parse_layer(&mut fields)?;
+            let origin = parse_origin(&mut fields)?;
+            let filename = strip_quotes(fields.next("filename")?);
+            let x: f32 = fields.parse("x position")?;
+            let y: f32 = fields.parse("y position")?;
+            let position = mint::Point2 { x, y };
+            if object_type == "4" || object_type == "Sprite" {
+                StoryboardObjectRef::Sprite(SpriteRef {
+                    layer,
+                    origin,
+                    filename: slice_of(line, filename),
+                    position,
+                    commands: Vec::new(),
+                })
+            } else {
+                let frame_count = fields.parse("frame count")?;
+                let frame_delay = fields.parse("frame delay")?;
+                let loop_type = parse_loop_type(&mut fields)?;
+                StoryboardObjectRef::Animation(AnimationRef {
+                    layer,
+                    origin,
+                    filename: slice_of(line, filename),
+                    position,
+                    frame_count,
+                    frame_delay,
+                    loop_type,
+                    commands: Vec::new(),
+                })
+            }
+        }
+        "5" | "Sample" => {
+            let time = fields.parse("time")?;
+            let layer = parse_layer(&mut fields)?;
+            let filename = strip_quotes(fields.next("filename")?);
+            let volume = fields.parse_opt("volume")?.unwrap_or(100);
+            StoryboardObjectRef::Sample(SampleRef {
+                time,
+                layer,
+                filename: slice_of(line, filename),
+                volume,
+            })
+        }
+        _ => {
+            return Err(fields.error(
+                OsuParserError::BadFormat,
+                "event type (Background, Video, Break, Sprite, Animation or Sample)",
+            ))
+        }
+    };
+    Ok(object)
+}
+
+fn read_command(commands: &mut Vec<Command>, line: &str) -> Result<(), LineError> {
+    let mut fields = Fields::new(line, ',');
+    let command_type = fields.next("command type")?.trim_start_matches([' ', '_']);
+    match command_type {
+        "L" => {
+            let start_time = fields.parse("start time")?;
+            let loop_count = fields.parse("loop count")?;
+            commands.push(Command::Loop {
+                start_time,
+                loop_count,
+                commands: Vec::new(),
+            });
+            return Ok(());
+        }
+        "T" => {
+            let trigger = fields.next("trigger name")?;
+            let start_time = fields.parse("start time")?;
+            let end_time = fields.parse("end time")?;
+            let group = fields.parse_opt("trigger group")?;
+            commands.push(Command::Trigger {
+                trigger: trigger.to_owned(),
+                start_time,
+                end_time,
+                group,
+                commands: Vec::new(),
+            });
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    const EASING: &str = "easing (0-34)";
+    let easing: u8 = fields.parse(EASING)?;
+    let easing =
+        Easing::try_from(easing).map_err(|_| fields.error(OsuParserError::BadFormat, EASING))?;
+    let start_time: Time = fields.parse("start time")?;
+    let end_time: Time = match fields.next("end time")? {
+        "" => start_time,
+        v => v.parse().map_err(|e| fields.error(e, "end time"))?,
+    };
+
+    let timing = (easing, start_time, end_time);
+    match command_type {
+        "F" => push_transforms(commands, timing, &mut fields, parse_f32, Command::Fade),
+        "M" => push_transforms(commands, timing, &mut fields, parse_point, Command::Move),
+        "MX" => push_transforms(commands, timing, &mut fields, parse_f32, Command::MoveX),
+        "MY" => push_transforms(commands, timing, &mut fields, parse_f32, Command::MoveY),
+        "S" => push_transforms(commands, timing, &mut fields, parse_f32, Command::Scale),
+        "V" => push_transforms(
+            commands,
+            timing,
+            &mut fields,
+            parse_vector,
+            Command::VectorScale,
+        ),
+        "R" => push_transforms(commands, timing, &mut fields, parse_f32, Command::Rotate),
+        "C" => push_transforms(commands, timing, &mut fields, parse_rgb, Command::Color),
+        "P" => {
+            const PARAMETER: &str = "parameter (H, V or A)";
+            let parameter = match fields.next(PARAMETER)? {
+                "H" => Parameter::FlipHorizontal,
+                "V" => Parameter::FlipVertical,
+                "A" => Parameter::AdditiveBlend,
+                _ => return Err(fields.error(OsuParserError::BadFormat, PARAMETER)),
+            };
+            commands.push(Command::Parameter {
+                easing,
+                start_time,
+                end_time,
+                parameter,
+            });
+            Ok(())
+        }
+        _ => Err(LineError {
+            error: OsuParserError::BadFormat,
+            field: Some(0),
+            offset: Some(line.len() - line.trim_start_matches([' ', '_']).len()),
+            expected: Some("command type (F, M, MX, MY, S, V, R, C, P, L or T)"),
+        }),
+    }
+}
+
+/// Commands may list more than two values, which is shorthand for a chain of commands that each
+/// take as long as the first one.
+fn push_transforms<T: Copy>(
+    commands: &mut Vec<Command>,
+    (easing, start_time, end_time): (Easing, Time, Time),
+    fields: &mut Fields,
+    parse: fn(&mut Fields) -> Result<T, LineError>,
+    command: fn(Transform<T>) -> Command,
+) -> Result<(), LineError> {
+    let mut values = vec![parse(fields)?];
+    while !fields.is_at_end() {
+        values.push(parse(fields)?);
+    }
+
+    if values.len() == 1 {
+        commands.push(command(Transform {
+            easing,
+            start_time,
+            end_time,
+            start: values[0],
+            end: values[0],
+        }));
+        return Ok(());
+    }
+
+    let duration = end_time - start_time;
+    for (i, pair) in values.windows(2).enumerate() {
+        let offset = duration * i as f64;
+        commands.push(command(Transform {
+            easing,
+            start_time: start_time + offset,
+            end_time: end_time + offset,
+            start: pair[0],
+            end: pair[1],
+        }));
+    }
+    Ok(())
+}
+
+fn parse_f32(fields: &mut Fields) -> Result<f32, LineError> {
+    fields.parse("value")
+}
+
+fn parse_point(fields: &mut Fields) -> Result<mint::Point2<f32>, LineError> {
+    Ok(mint::Point2 {
+        x: fields.parse("x value")?,
+        y: fields.parse("y value")?,
+    })
+}
+
+fn parse_vector(fields: &mut Fields) -> Result<mint::Vector2<f32>, LineError> {
+    Ok(mint::Vector2 {
+        x: fields.parse("x scale")?,
+        y: fields.parse("y scale")?,
+    })
+}
+
+fn parse_rgb(fields: &mut Fields) -> Result<RGB<u8>, LineError> {
+    Ok(RGB {
+        r: fields.parse("red component")?,
+        g: fields.parse("green component")?,
+        b: fields.parse("blue component")?,
+    })
+}
@@ -0,0 +1,534 @@
+use std::io::Write;
+
+use osu_types::storyboard::{Command, Parameter, Storyboard, StoryboardObject, Transform};
+use osu_types::{
+    BeatmapDifficulty, BeatmapEditor, BeatmapGeneralData, BeatmapMetadata, Countdown, CurveType,
+    Event, HitObject, HitSample, HitSound, Mode, OverlayPosition, SampleSet, SpecificHitObject,
+    TimingPoint, RGB,
+};
+
+use crate::{error::OsuParserError, Beatmap, MAX_FORMAT_VERSION, MIN_FORMAT_VERSION};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BeatmapWriteOptions {
+    /// File format version to emit, [`Beatmap::format_version`] if `None`. Data that the version
+    /// doesn't know about is left out.
+    pub version: Option<u8>,
+}
+
+pub fn save_file(
+    path: impl AsRef<std::path::Path>,
+    beatmap: &Beatmap,
+    options: BeatmapWriteOptions,
+) -> Result<(), OsuParserError> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    write_beatmap(&mut writer, beatmap, options)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn save_content(
+    beatmap: &Beatmap,
+    options: BeatmapWriteOptions,
+) -> Result<String, OsuParserError> {
+    let mut content = Vec::new();
+    write_beatmap(&mut content, beatmap, options)?;
+    // Everything written is either ascii or copied from a str.
+    Ok(String::from_utf8(content).unwrap())
+}
+
+pub fn write_beatmap(
+    mut writer: impl Write,
+    beatmap: &Beatmap,
+    options: BeatmapWriteOptions,
+) -> Result<(), OsuParserError> {
+    let version = options.version.unwrap_or(beatmap.format_version);
+    if version < MIN_FORMAT_VERSION {
+        return Err(OsuParserError::VersionTooOld(version));
+    } else if version > MAX_FORMAT_VERSION {
+        return Err(OsuParserError::VersionTooNew(version));
+    }
+
+    writeln!(writer, "osu file format v{}", version)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[General]")?;
+    write_general(&mut writer, &beatmap.info.general_data)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Editor]")?;
+    write_editor(&mut writer, &beatmap.editor)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Metadata]")?;
+    write_metadata(&mut writer, &beatmap.info.metadata, version)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Difficulty]")?;
+    write_difficulty(&mut writer, &beatmap.info.difficulty, version)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[Events]")?;
+    for event in &beatmap.events {
+        write_event(&mut writer, event)?;
+    }
+    write_storyboard(&mut writer, &beatmap.storyboard)?;
+    writeln!(writer)?;
+
+    writeln!(writer, "[TimingPoints]")?;
+    for timing_point in &beatmap.timing_points {
+        write_timing_point(&mut writer, timing_point, version)?;
+    }
+    writeln!(writer)?;
+
+    if !beatmap.colors.is_empty()
+        || beatmap.slider_track_override.is_some()
+        || beatmap.slider_border.is_some()
+    {
+        writeln!(writer, "[Colours]")?;
+        write_colors(&mut writer, beatmap)?;
+        writeln!(writer)?;
+    }
+
+    writeln!(writer, "[HitObjects]")?;
+    for hit_object in &beatmap.hit_objects {
+        write_hitobject(&mut writer, hit_object, version)?;
+    }
+
+    Ok(())
+}
+
+fn write_general(mut writer: impl Write, general: &BeatmapGeneralData) -> std::io::Result<()> {
+    writeln!(writer, "AudioFilename: {}", general.audio_file_name)?;
+    writeln!(writer, "AudioLeadIn: {}", general.audio_lead_in)?;
+    if let Some(hash) = &general.audio_hash {
+        writeln!(writer, "AudioHash: {}", hash)?;
+    }
+    // -1 is how the file says there is no preview point.
+    writeln!(
+        writer,
+        "PreviewTime: {}",
+        general.preview_time.unwrap_or(-1.0)
+    )?;
+    let countdown = match general.countdown {
+        None => 0,
+        Some(Countdown::Normal) => 1,
+        Some(Countdown::Half) => 2,
+        Some(Countdown::Double) => 3,
+    };
+    writeln!(writer, "Countdown: {}", countdown)?;
+    writeln!(writer, "SampleSet: {}", sample_set_name(general.sample_set))?;
+    writeln!(writer, "StackLeniency: {}", general.stack_leniency)?;
+    let mode = match general.mode {
+        Mode::Osu => 0,
+        Mode::Taiko => 1,
+        Mode::Catch => 2,
+        Mode::Mania => 3,
+    };
+    writeln!(writer, "Mode: {}", mode)?;
+    writeln!(
+        writer,
+        "LetterboxInBreaks: {}",
+        general.letterbox_in_breaks as u8
+    )?;
+    // Like the client, only write the newer keys when they aren't the default.
+    if !general.story_fire_in_front {
+        writeln!(writer, "StoryFireInFront: 0")?;
+    }
+    if general.use_skin_sprites {
+        writeln!(writer, "UseSkinSprites: 1")?;
+    }
+    if general.always_show_playfield {
+        writeln!(writer, "AlwaysShowPlayfield: 1")?;
+    }
+    if general.overlay_position != OverlayPosition::NoChange {
+        writeln!(writer, "OverlayPosition: {:?}", general.overlay_position)?;
+    }
+    if let Some(skin) = &general.skin_preference {
+        writeln!(writer, "SkinPreference:{}", skin)?;
+    }
+    if general.epilepsy_warning {
+        writeln!(writer, "EpilepsyWarning: 1")?;
+    }
+    if general.countdown_offset > 0 {
+        writeln!(writer, "CountdownOffset: {}", general.countdown_offset)?;
+    }
+    if general.special_style {
+        writeln!(writer, "SpecialStyle: 1")?;
+    }
+    writeln!(
+        writer,
+        "WidescreenStoryboard: {}",
+        general.widescreen_storyboard as u8
+    )?;
+    if general.samples_match_playback_rate {
+        writeln!(writer, "SamplesMatchPlaybackRate: 1")?;
+    }
+    Ok(())
+}
+
+fn write_editor(mut writer: impl Write, editor: &BeatmapEditor) -> std::io::Result<()> {
+    if !editor.bookmarks.is_empty() {
+        let bookmarks = editor
+            .bookmarks
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>();
+        writeln!(writer, "Bookmarks: {}", bookmarks.join(","))?;
+    }
+    writeln!(writer, "DistanceSpacing: {}", editor.distance_spacing)?;
+    writeln!(writer, "BeatDivisor: {}", editor.beat_divisor)?;
+    writeln!(writer, "GridSize: {}", editor.grid_size)?;
+    writeln!(writer, "TimelineZoom: {}", editor.timeline_zoom)?;
+    Ok(())
+}
+
+fn write_metadata(
+    mut writer: impl Write,
+    metadata: &BeatmapMetadata,
+    version: u8,
+) -> std::io::Result<()> {
+    writeln!(writer, "Title:{}", metadata.title)?;
+    if version >= 10 {
+        writeln!(writer, "TitleUnicode:{}", metadata.title_unicode)?;
+    }
+    writeln!(writer, "Artist:{}", metadata.artist)?;
+    if version >= 10 {
+        writeln!(writer, "ArtistUnicode:{}", metadata.artist_unicode)?;
+    }
+    writeln!(writer, "Creator:{}", metadata.creator)?;
+    writeln!(writer, "Version:{}", metadata.version)?;
+    writeln!(writer, "Source:{}", metadata.source)?;
+    writeln!(writer, "Tags:{}", metadata.tags)?;
+    if version >= 10 {
+        writeln!(writer, "BeatmapID:{}", metadata.beatmap_id)?;
+        writeln!(writer, "BeatmapSetID:{}", metadata.beatmap_set_id)?;
+    }
+    Ok(())
+}
+
+fn write_difficulty(
+    mut writer: impl Write,
+    difficulty: &BeatmapDifficulty,
+    version: u8,
+) -> std::io::Result<()> {
+    writeln!(writer, "HPDrainRate:{}", difficulty.hp)?;
+    writeln!(writer, "CircleSize:{}", difficulty.cs)?;
+    writeln!(writer, "OverallDifficulty:{}", difficulty.od)?;
+    if version >= 8 {
+        writeln!(writer, "ApproachRate:{}", difficulty.ar)?;
+    }
+    writeln!(writer, "SliderMultiplier:{}", difficulty.slider_multiplier)?;
+    writeln!(writer, "SliderTickRate:{}", difficulty.slider_tick_rate)?;
+    Ok(())
+}
+
+fn write_event(mut writer: impl Write, event: &Event) -> std::io::Result<()> {
+    match event {
+        Event::Background { filename, offset } => {
+            writeln!(writer, "0,0,\"{}\",{},{}", filename, offset.x, offset.y)
+        }
+        Event::Video {
+            start_time,
+            filename,
+            offset,
+        } => writeln!(
+            writer,
+            "Video,{},\"{}\",{},{}",
+            start_time, filename, offset.x, offset.y
+        ),
+        Event::Break(range) => writeln!(writer, "2,{},{}", range.start, range.end),
+        Event::Color { start_time, color } => {
+            writeln!(writer, "3,{},{}", start_time, format_color(*color))
+        }
+    }
+}
+
+fn write_storyboard(mut writer: impl Write, storyboard: &Storyboard) -> std::io::Result<()> {
+    for object in &storyboard.objects {
+        match object {
+            StoryboardObject::Sprite(sprite) => writeln!(
+                writer,
+                "Sprite,{:?},{:?},\"{}\",{},{}",
+                sprite.layer, sprite.origin, sprite.filename, sprite.position.x, sprite.position.y
+            )?,
+            StoryboardObject::Animation(animation) => writeln!(
+                writer,
+                "Animation,{:?},{:?},\"{}\",{},{},{},{},{:?}",
+                animation.layer,
+                animation.origin,
+                animation.filename,
+                animation.position.x,
+                animation.position.y,
+                animation.frame_count,
+                animation.frame_delay,
+                animation.loop_type
+            )?,
+            StoryboardObject::Sample(sample) => writeln!(
+                writer,
+                "Sample,{},{},\"{}\",{}",
+                sample.time, sample.layer as u8, sample.filename, sample.volume
+            )?,
+        }
+        write_commands(&mut writer, object.commands(), 1)?;
+    }
+    Ok(())
+}
+
+fn write_commands(
+    writer: &mut impl Write,
+    commands: &[Command],
+    depth: usize,
+) -> std::io::Result<()> {
+    let indent = " ".repeat(depth);
+    for command in commands {
+        match command {
+            Command::Fade(t) => write_transform(writer, &indent, "F", t, |v| v.to_string())?,
+            Command::Move(t) => {
+                write_transform(writer, &indent, "M", t, |v| format!("{},{}", v.x, v.y))?
+            }
+            Command::MoveX(t) => write_transform(writer, &indent, "MX", t, |v| v.to_string())?,
+            Command::MoveY(t) => write_transform(writer, &indent, "MY", t, |v| v.to_string())?,
+            Command::Scale(t) => write_transform(writer, &indent, "S", t, |v| v.to_string())?,
+            Command::VectorScale(t) => {
+                write_transform(writer, &indent, "V", t, |v| format!("{},{}", v.x, v.y))?
+            }
+            Command::Rotate(t) => write_transform(writer, &indent, "R", t, |v| v.to_string())?,
+            Command::Color(t) => write_transform(writer, &indent, "C", t, |v| format_color(*v))?,
+            Command::Parameter {
+                easing,
+                start_time,
+                end_time,
+                parameter,
+            } => {
+                let parameter = match parameter {
+                    Parameter::FlipHorizontal => "H",
+                    Parameter::FlipVertical => "V",
+                    Parameter::AdditiveBlend => "A",
+                };
+                writeln!(
+                    writer,
+                    "{}P,{},{},{},{}",
+                    indent, *easing as u8, start_time, end_time, parameter
+                )?;
+            }
+            Command::Loop {
+                start_time,
+                loop_count,
+                commands,
+            } => {
+                writeln!(writer, "{}L,{},{}", indent, start_time, loop_count)?;
+                write_commands(writer, commands, depth + 1)?;
+            }
+            Command::Trigger {
+                trigger,
+                start_time,
+                end_time,
+                group,
+                commands,
+            } => {
+                write!(
+                    writer,
+                    "{}T,{},{},{}",
+                    indent, trigger, start_time, end_time
+                )?;
+                if let Some(group) = group {
+                    write!(writer, ",{}", group)?;
+                }
+                writeln!(writer)?;
+                write_commands(writer, commands, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_transform<T>(
+    writer: &mut impl Write,
+    indent: &str,
+    command: &str,
+    transform: &Transform<T>,
+    format_value: impl Fn(&T) -> String,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{}{},{},{},{},{},{}",
+        indent,
+        command,
+        transform.easing as u8,
+        transform.start_time,
+        transform.end_time,
+        format_value(&transform.start),
+        format_value(&transform.end)
+    )
+}
+
+fn write_timing_point(
+    mut writer: impl Write,
+    timing_point: &TimingPoint,
+    version: u8,
+) -> std::io::Result<()> {
+    write!(writer, "{},{}", timing_point.time, timing_point.beat_length)?;
+    if version >= 5 {
+        write!(
+            writer,
+            ",{},{},{},{}",
+            timing_point.meter,
+            sample_set_id(timing_point.sample_set),
+            timing_point.sample_index,
+            timing_point.volume
+        )?;
+    }
+    if version >= 6 {
+        write!(
+            writer,
+            ",{},{}",
+            timing_point.uninherited as u8,
+            timing_point.effects.bits()
+        )?;
+    }
+    writeln!(writer)
+}
+
+fn write_colors(mut writer: impl Write, beatmap: &Beatmap) -> std::io::Result<()> {
+    for (i, color) in beatmap.colors.iter().enumerate() {
+        writeln!(writer, "Combo{} : {}", i + 1, format_color(*color))?;
+    }
+    if let Some(color) = beatmap.slider_track_override {
+        writeln!(writer, "SliderTrackOverride : {}", format_color(color))?;
+    }
+    if let Some(color) = beatmap.slider_border {
+        writeln!(writer, "SliderBorder : {}", format_color(color))?;
+    }
+    Ok(())
+}
+
+fn format_color(color: RGB<u8>) -> String {
+    format!("{},{},{}", color.r, color.g, color.b)
+}
+
+fn write_hitobject(
+    mut writer: impl Write,
+    hit_object: &HitObject,
+    version: u8,
+) -> std::io::Result<()> {
+    write!(
+        writer,
+        "{},{},{},{},{}",
+        hit_object.position.x,
+        hit_object.position.y,
+        hit_object.time,
+        hit_object.object_type().bits(),
+        hit_object.hit_sound.bits()
+    )?;
+
+    let write_sample = version >= 12 && has_hit_sample(&hit_object.hit_sample);
+    match &hit_object.specific {
+        SpecificHitObject::Circle => {}
+        SpecificHitObject::Slider {
+            curve_type,
+            curve_points,
+            slides,
+            length,
+            edge_sounds,
+            edge_sets,
+        } => {
+            write!(writer, ",{}", curve_type_id(*curve_type))?;
+            for point in curve_points {
+                write!(writer, "|{}:{}", point.x, point.y)?;
+            }
+            write!(writer, ",{},{}", slides, length)?;
+            // The hit sample comes after the edges, so they have to be written for it as well.
+            if !edge_sounds.is_empty() || write_sample {
+                let edges = if edge_sounds.is_empty() {
+                    usize::from(*slides) + 1
+                } else {
+                    edge_sounds.len()
+                };
+                let edge_sounds = (0..edges)
+                    .map(|i| {
+                        let sound = edge_sounds.get(i).copied().unwrap_or_else(HitSound::empty);
+                        sound.bits().to_string()
+                    })
+                    .collect::<Vec<_>>();
+                // Sliders without sets still need one per edge so the hit sample can follow.
+                let edge_sets = (0..edges)
+                    .map(|i| {
+                        let set = edge_sets.get(i).copied().unwrap_or_default();
+                        format!(
+                            "{}:{}",
+                            sample_set_id(set.normal_set),
+                            sample_set_id(set.addition_set)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                write!(writer, ",{},{}", edge_sounds.join("|"), edge_sets.join("|"))?;
+            }
+        }
+        SpecificHitObject::Spinner { end_time } => {
+            write!(writer, ",{}", end_time)?;
+        }
+        SpecificHitObject::ManiaHold { end_time } => {
+            write!(writer, ",{}", end_time)?;
+            if write_sample {
+                write!(writer, ":")?;
+                write_hit_sample(&mut writer, &hit_object.hit_sample)?;
+            }
+            return writeln!(writer);
+        }
+    }
+
+    if write_sample {
+        write!(writer, ",")?;
+        write_hit_sample(&mut writer, &hit_object.hit_sample)?;
+    }
+    writeln!(writer)
+}
+
+/// Whether the hit sample has to be written, as opposed to being left to the parser's defaults.
+fn has_hit_sample(hit_sample: &HitSample) -> bool {
+    hit_sample.filename.is_some()
+        || hit_sample.normal_set.is_some()
+        || hit_sample.addition_set.is_some()
+        || hit_sample.index != 0
+        || hit_sample.volume != 100
+}
+
+fn write_hit_sample(mut writer: impl Write, hit_sample: &HitSample) -> std::io::Result<()> {
+    write!(
+        writer,
+        "{}:{}:{}:{}:{}",
+        sample_set_id(hit_sample.normal_set),
+        sample_set_id(hit_sample.addition_set),
+        hit_sample.index,
+        hit_sample.volume,
+        hit_sample.filename.as_deref().unwrap_or("")
+    )
+}
+
+fn sample_set_name(sample_set: SampleSet) -> &'static str {
+    match sample_set {
+        SampleSet::Normal => "Normal",
+        SampleSet::Soft => "Soft",
+        SampleSet::Drum => "Drum",
+    }
+}
+
+fn sample_set_id(sample_set: Option<SampleSet>) -> u8 {
+    match sample_set {
+        None => 0,
+        Some(SampleSet::Normal) => 1,
+        Some(SampleSet::Soft) => 2,
+        Some(SampleSet::Drum) => 3,
+    }
+}
+
+fn curve_type_id(curve_type: CurveType) -> &'static str {
+    match curve_type {
+        CurveType::Bezier => "B",
+        CurveType::Catmull => "C",
+        CurveType::Perfect => "P",
+        CurveType::Linear => "L",
+    }
+}
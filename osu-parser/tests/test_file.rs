@@ -1,5 +1,49 @@
-use osu_parser::{load_file, BeatmapParseOptions};
-use osu_types::{Mode, SampleSet};
+#![allow(clippy::bool_assert_comparison)]
+
+use std::borrow::Cow;
+
+use osu_parser::{
+    checksum_hex, error::OsuParserError, load_content, load_content_ref,
+    load_content_with_warnings, load_file, load_file_with_checksum, load_reader,
+    load_storyboard_content, save_content, scan_header_file, BeatmapParseOptions,
+    BeatmapWriteOptions, EventRef, HeaderScanOptions, StoryboardObjectRef, DEFAULT_COMBO_COLORS,
+};
+use osu_types::{
+    osu_point,
+    storyboard::{Command, Easing, Layer, LoopType, Origin, StoryboardObject, Transform},
+    BeatmapFileSection, ComboColor, Countdown, EdgeSet, Effects, Event, HitObjectType, HitSound,
+    Mode, OverlayPosition, SampleSet, SpecificHitObject, RGB,
+};
+use osu_utils::StackingAlgorithm;
+
+/// A v14 beatmap with the required keys of [General], [Metadata] and [Difficulty], followed by
+/// `objects`, which holds the other sections.
+fn map_with_objects(objects: &str) -> String {
+    format!(
+        "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+Mode: 0
+
+[Metadata]
+Title:Title
+Artist:Artist
+Creator:Creator
+Version:Version
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1
+SliderTickRate:1
+
+{}",
+        objects
+    )
+}
 
 #[test]
 pub fn parse_very_old() {
@@ -10,11 +54,34 @@ pub fn parse_very_old() {
     .unwrap();
 
     assert_eq!(beatmap.info.general_data.audio_file_name, "20.mp3");
+    // Missing from the file, so the documented defaults are used.
+    assert_eq!(beatmap.info.general_data.preview_time, None);
+    assert_eq!(beatmap.info.general_data.countdown, Some(Countdown::Normal));
+    assert_eq!(beatmap.info.general_data.stack_leniency, 0.7);
 
     assert_eq!(beatmap.info.metadata.title, "DISCO★PRINCE");
     assert_eq!(beatmap.info.metadata.artist, "Kenji Ninuma");
     assert_eq!(beatmap.info.metadata.creator, "peppy");
     assert_eq!(beatmap.info.metadata.version, "Normal");
+
+    assert_eq!(
+        beatmap.events[4],
+        Event::Color {
+            start_time: 0.0,
+            color: RGB {
+                r: 54,
+                g: 140,
+                b: 191
+            }
+        }
+    );
+    assert!(beatmap.storyboard.is_empty());
+
+    // No [Colours] section, so the default colours are used, and none are written back.
+    assert!(beatmap.colors.is_empty());
+    assert_eq!(beatmap.combo_colors(), &DEFAULT_COMBO_COLORS);
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    assert!(!content.contains("[Colours]"));
 }
 
 #[test]
@@ -26,8 +93,8 @@ pub fn parse_old() {
     .unwrap();
 
     assert_eq!(beatmap.info.general_data.audio_file_name, "tearrain.mp3");
-    assert_eq!(beatmap.info.general_data.audio_lead_in, 1500);
-    assert_eq!(beatmap.info.general_data.preview_time, 195852);
+    assert_eq!(beatmap.info.general_data.audio_lead_in, 1500.0);
+    assert_eq!(beatmap.info.general_data.preview_time, Some(195852.0));
     assert_eq!(beatmap.info.general_data.countdown, None);
     assert_eq!(beatmap.info.general_data.sample_set, SampleSet::Soft);
     assert_eq!(beatmap.info.general_data.stack_leniency, 0.5);
@@ -45,6 +112,44 @@ pub fn parse_old() {
     assert_eq!(beatmap.info.metadata.tags, "Amateras Records Mizuyosi Radical Destruction 魔法少女達の百年祭 The Centennial Festival for Magical Girls 東方紅魔郷 ～ the Embodiment of Scarlet Devil Extra stage theme monthly beatmapping contest two");
     assert_eq!(beatmap.info.metadata.beatmap_id, 351189);
     assert_eq!(beatmap.info.metadata.beatmap_set_id, 140662);
+
+    assert_eq!(beatmap.editor.bookmarks, vec![852.0, 113352.0, 195852.0]);
+    assert_eq!(beatmap.editor.distance_spacing, 1.2);
+    // Missing from the file
+    assert_eq!(beatmap.editor.timeline_zoom, 1.0);
+
+    assert_eq!(
+        beatmap.colors,
+        vec![
+            ComboColor {
+                r: 255,
+                g: 17,
+                b: 17
+            },
+            ComboColor {
+                r: 71,
+                g: 204,
+                b: 254
+            },
+            ComboColor {
+                r: 253,
+                g: 234,
+                b: 123
+            },
+            ComboColor {
+                r: 218,
+                g: 121,
+                b: 255
+            },
+            ComboColor {
+                r: 254,
+                g: 146,
+                b: 184
+            },
+        ]
+    );
+    assert_eq!(beatmap.slider_track_override, None);
+    assert_eq!(beatmap.slider_border, None);
 }
 
 #[test]
@@ -56,8 +161,8 @@ pub fn parse_modern() {
     .unwrap();
 
     assert_eq!(beatmap.info.general_data.audio_file_name, "audio.mp3");
-    assert_eq!(beatmap.info.general_data.audio_lead_in, 0);
-    assert_eq!(beatmap.info.general_data.preview_time, 35569);
+    assert_eq!(beatmap.info.general_data.audio_lead_in, 0.0);
+    assert_eq!(beatmap.info.general_data.preview_time, Some(35569.0));
     assert_eq!(beatmap.info.general_data.countdown, None);
     assert_eq!(beatmap.info.general_data.sample_set, SampleSet::Soft);
     assert_eq!(beatmap.info.general_data.stack_leniency, 0.4);
@@ -75,4 +180,1007 @@ pub fn parse_modern() {
     assert_eq!(beatmap.info.metadata.tags, "ホロライブ hololive vtuber virtual youtuber chinozo japanese pop jpop j-pop さんかくずわり sankaku zuwari cover flower goodbye declaration 引き籠り hikikomori matha -_matha_- petal wanpachi dada deppy deppyforce");
     assert_eq!(beatmap.info.metadata.beatmap_id, 3020125);
     assert_eq!(beatmap.info.metadata.beatmap_set_id, 1471082);
+
+    assert_eq!(beatmap.editor.bookmarks.len(), 13);
+    assert_eq!(beatmap.editor.bookmarks[0], 2128.0);
+    assert_eq!(beatmap.editor.distance_spacing, 0.2);
+    assert_eq!(beatmap.editor.beat_divisor, 8);
+    assert_eq!(beatmap.editor.grid_size, 32);
+    assert_eq!(beatmap.editor.timeline_zoom, 3.379999);
+
+    let kiai = beatmap
+        .timing_points
+        .iter()
+        .find(|t| t.time == 36010.0)
+        .unwrap();
+    assert!(kiai.effects.contains(Effects::KIAI));
+    assert!(!kiai.effects.contains(Effects::OMIT_FIRST_BARLINE));
+
+    let slider = beatmap
+        .hit_objects
+        .iter()
+        .find(|o| o.time == 17304.0)
+        .unwrap();
+    assert_eq!(
+        slider.object_type(),
+        HitObjectType::SLIDER | HitObjectType::NEW_COMBO
+    );
+    match &slider.specific {
+        SpecificHitObject::Slider {
+            edge_sounds,
+            edge_sets,
+            ..
+        } => {
+            assert_eq!(edge_sounds, &vec![HitSound::WHISTLE, HitSound::empty()]);
+            assert_eq!(
+                edge_sets,
+                &vec![
+                    EdgeSet {
+                        normal_set: Some(SampleSet::Drum),
+                        addition_set: Some(SampleSet::Soft)
+                    },
+                    EdgeSet {
+                        normal_set: Some(SampleSet::Drum),
+                        addition_set: None
+                    }
+                ]
+            );
+        }
+        _ => panic!("expected a slider"),
+    }
+}
+
+#[test]
+pub fn write_roundtrip() {
+    for path in [
+        "Kenji Ninuma - DISCOüÜPRINCE (peppy) [Normal].osu",
+        "cYsmix feat. Emmy - Tear Rain (jonathanlfj) [Insane].osu",
+        "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu",
+    ] {
+        let beatmap = load_file(path, BeatmapParseOptions::default()).unwrap();
+        // Beatmaps are written in their own version by default.
+        let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+        let header = format!("osu file format v{}", beatmap.format_version);
+        assert!(content.starts_with(&header));
+
+        let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+        assert_eq!(beatmap, reparsed, "{} did not survive a round trip", path);
+
+        let options = BeatmapWriteOptions { version: Some(14) };
+        let content = save_content(&beatmap, options).unwrap();
+        assert!(content.starts_with("osu file format v14"));
+
+        let mut reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+        assert_eq!(reparsed.format_version, 14);
+        reparsed.format_version = beatmap.format_version;
+        assert_eq!(beatmap, reparsed, "{} did not survive a round trip", path);
+    }
+}
+
+#[test]
+pub fn write_old_version() {
+    let beatmap = load_file(
+        "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu",
+        BeatmapParseOptions::default(),
+    )
+    .unwrap();
+    let content = save_content(&beatmap, BeatmapWriteOptions { version: Some(7) }).unwrap();
+    assert!(content.starts_with("osu file format v7"));
+    assert!(!content.contains("ApproachRate"));
+
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(reparsed.info.metadata.title, beatmap.info.metadata.title);
+    assert_eq!(reparsed.info.difficulty.ar, beatmap.info.difficulty.od);
+    assert_eq!(reparsed.hit_objects.len(), beatmap.hit_objects.len());
+
+    assert!(save_content(&beatmap, BeatmapWriteOptions { version: Some(15) }).is_err());
+}
+
+#[test]
+pub fn write_general_defaults() {
+    // No Countdown or StackLeniency, and no preview point.
+    let content = map_with_objects("[HitObjects]\n256,192,1000,1,0,0:0:0:0:\n")
+        .replace("Mode: 0", "Mode: 0\nPreviewTime: -1");
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+
+    // The written values have to mean the same to the client as the missing keys.
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert!(lines.contains(&"PreviewTime: -1"));
+    assert!(lines.contains(&"Countdown: 1"));
+    assert!(lines.contains(&"StackLeniency: 0.7"));
+
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn write_slider_hit_sample() {
+    let content = map_with_objects(
+        "[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+0,0,1500,2,0,L|200:0,2,200
+",
+    );
+    let mut beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    let slider = &mut beatmap.hit_objects[0];
+    slider.hit_sample.filename = Some("hit.wav".to_string());
+    if let SpecificHitObject::Slider {
+        edge_sounds,
+        edge_sets,
+        ..
+    } = &mut slider.specific
+    {
+        edge_sounds.clear();
+        edge_sets.clear();
+    }
+
+    // Without edge sounds the edges are written with defaults, so the hit sample can follow.
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    assert!(content.contains("0,0,1500,2,0,L|200:0,2,200,0|0|0,0:0|0:0|0:0,0:0:0:100:hit.wav"));
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(
+        reparsed.hit_objects[0].hit_sample,
+        beatmap.hit_objects[0].hit_sample
+    );
+}
+
+#[test]
+pub fn parse_colors() {
+    let content = map_with_objects(
+        "[Colours]
+Combo2 : 0,255,0
+Combo1 : 255,0,0
+SliderTrackOverride : 1,2,3
+SliderBorder : 4,5,6
+",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(
+        beatmap.colors,
+        vec![
+            ComboColor { r: 255, g: 0, b: 0 },
+            ComboColor { r: 0, g: 255, b: 0 }
+        ]
+    );
+    assert_eq!(
+        beatmap.slider_track_override,
+        Some(RGB { r: 1, g: 2, b: 3 })
+    );
+    assert_eq!(beatmap.slider_border, Some(RGB { r: 4, g: 5, b: 6 }));
+
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn parse_storyboard() {
+    let content = map_with_objects(
+        "[Events]
+//Background and Video events
+0,0,\"bg.jpg\",0,0
+//Storyboard Layer 0 (Background)
+Sprite,Background,Centre,\"sb/light.png\",320,240
+ F,0,1000,2000,0,1,0
+ L,3000,4
+  M,1,0,500,320,240,330,250
+ P,0,1000,,A
+Animation,Foreground,TopLeft,\"sb/anim.png\",0,0,8,50,LoopOnce
+_C,0,100,200,255,255,255,0,0,0
+5,1500,3,\"sb/hit.wav\",70
+",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.events.len(), 1);
+
+    let objects = &beatmap.storyboard.objects;
+    assert_eq!(objects.len(), 3);
+    match &objects[0] {
+        StoryboardObject::Sprite(sprite) => {
+            assert_eq!(sprite.layer, Layer::Background);
+            assert_eq!(sprite.origin, Origin::Centre);
+            assert_eq!(sprite.filename, "sb/light.png");
+            assert_eq!(sprite.commands.len(), 4);
+            // The shorthand fade expands into two consecutive fades.
+            assert_eq!(
+                sprite.commands[1],
+                Command::Fade(Transform {
+                    easing: Easing::Linear,
+                    start_time: 2000.0,
+                    end_time: 3000.0,
+                    start: 1.0,
+                    end: 0.0,
+                })
+            );
+            match &sprite.commands[2] {
+                Command::Loop {
+                    start_time,
+                    loop_count,
+                    commands,
+                } => {
+                    assert_eq!(*start_time, 3000.0);
+                    assert_eq!(*loop_count, 4);
+                    assert_eq!(commands.len(), 1);
+                }
+                command => panic!("expected a loop, got {:?}", command),
+            }
+        }
+        object => panic!("expected a sprite, got {:?}", object),
+    }
+    match &objects[1] {
+        StoryboardObject::Animation(animation) => {
+            assert_eq!(animation.frame_count, 8);
+            assert_eq!(animation.frame_delay, 50.0);
+            assert_eq!(animation.loop_type, LoopType::LoopOnce);
+            assert_eq!(animation.commands.len(), 1);
+        }
+        object => panic!("expected an animation, got {:?}", object),
+    }
+    match &objects[2] {
+        StoryboardObject::Sample(sample) => {
+            assert_eq!(sample.time, 1500.0);
+            assert_eq!(sample.layer, Layer::Foreground);
+            assert_eq!(sample.volume, 70);
+        }
+        object => panic!("expected a sample, got {:?}", object),
+    }
+
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn parse_storyboard_file() {
+    let content = "[Variables]
+$start=100
+$end=200
+$on=1
+
+[Events]
+//Storyboard Layer 0 (Background)
+Sprite,Background,Centre,\"bg.png\",320,240
+ F,0,$start,$end,$on,0
+Sprite,Foreground,Centre,\"fg.png\",320,240
+";
+    let shared = load_storyboard_content(content).unwrap();
+    assert_eq!(shared.objects.len(), 2);
+    assert_eq!(
+        shared.objects[0].commands(),
+        &[Command::Fade(Transform {
+            easing: Easing::Linear,
+            start_time: 100.0,
+            end_time: 200.0,
+            start: 1.0,
+            end: 0.0,
+        })]
+    );
+
+    let difficulty = load_storyboard_content(
+        "[Events]
+Sprite,Background,Centre,\"diff.png\",320,240
+",
+    )
+    .unwrap();
+    let merged = shared.merge(&difficulty);
+    let background = merged.layer(Layer::Background).collect::<Vec<_>>();
+    assert_eq!(background.len(), 2);
+    match background[1] {
+        StoryboardObject::Sprite(sprite) => assert_eq!(sprite.filename, "diff.png"),
+        object => panic!("expected a sprite, got {:?}", object),
+    }
+}
+
+#[test]
+pub fn parse_error_location() {
+    let content = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,x,1500,1,0,0:0:0:0:
+";
+    let error = load_content(content, BeatmapParseOptions::default()).unwrap_err();
+    assert_eq!(error.section, Some(BeatmapFileSection::HitObjects));
+    assert_eq!(error.line_number, Some(8));
+    assert_eq!(error.field, Some(1));
+    assert_eq!(error.column, Some(5));
+    assert_eq!(error.line.as_deref(), Some("256,x,1500,1,0,0:0:0:0:"));
+    assert!(error.expected.is_some());
+
+    let message = error.to_string();
+    assert!(message.contains("at line 8, column 5"));
+    assert!(message.contains("8 | 256,x,1500,1,0,0:0:0:0:"));
+    assert!(message.contains("  |     ^ expected"));
+}
+
+#[test]
+pub fn parse_lenient() {
+    let content = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+Countdown: 7
+UnknownKey: 1
+
+[Metadata]
+Title:Title
+Artist:Artist
+Creator:Creator
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[Events]
+0,0,bg.jpg,0,0
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,x,1500,1,0,0:0:0:0:
+256,192,2000,1,0,0:0:0:0:
+";
+    assert!(load_content(content, BeatmapParseOptions::default()).is_err());
+
+    let options = BeatmapParseOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (beatmap, warnings) = load_content_with_warnings(content, options).unwrap();
+    // The broken countdown is skipped, so the default is used.
+    assert_eq!(beatmap.info.general_data.countdown, Some(Countdown::Normal));
+    assert_eq!(beatmap.info.metadata.version, "");
+    assert_eq!(
+        beatmap.events[0],
+        Event::Background {
+            filename: "bg.jpg".to_owned(),
+            offset: osu_point(0, 0)
+        }
+    );
+    assert_eq!(beatmap.hit_objects.len(), 2);
+
+    let lines: Vec<_> = warnings.iter().map(|w| w.line_number).collect();
+    assert_eq!(lines, vec![Some(5), Some(6), Some(25), None]);
+    assert_eq!(warnings[0].section, Some(BeatmapFileSection::General));
+    assert_eq!(warnings[3].section, Some(BeatmapFileSection::Metadata));
+}
+
+#[test]
+pub fn parse_general() {
+    let content = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+StoryFireInFront: 0
+UseSkinSprites: 1
+OverlayPosition: Above
+SkinPreference:Default
+EpilepsyWarning: 1
+CountdownOffset: 2
+SpecialStyle: 1
+SamplesMatchPlaybackRate: 1
+EditorBookmarks: 100,200
+
+[Metadata]
+Title:Title
+Artist:Artist
+Creator:Creator
+Version:Version
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+SliderMultiplier:1.4
+SliderTickRate:1
+";
+    let beatmap = load_content(content, BeatmapParseOptions::default()).unwrap();
+    let general = &beatmap.info.general_data;
+    assert_eq!(general.story_fire_in_front, false);
+    assert_eq!(general.use_skin_sprites, true);
+    assert_eq!(general.always_show_playfield, false);
+    assert_eq!(general.overlay_position, OverlayPosition::Above);
+    assert_eq!(general.skin_preference.as_deref(), Some("Default"));
+    assert_eq!(general.epilepsy_warning, true);
+    assert_eq!(general.countdown_offset, 2);
+    assert_eq!(general.special_style, true);
+    assert_eq!(general.samples_match_playback_rate, true);
+    assert_eq!(beatmap.editor.bookmarks, vec![100.0, 200.0]);
+
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn parse_reader() {
+    let mut content = b"osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+
+[Metadata]
+Title:Title
+Artist:Artist
+Creator:Creator
+Version:Version
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[Events]
+0,0,\"bg.jpg\",0,0
+
+[HitObjects]
+"
+    .to_vec();
+    content.extend_from_slice(b"256,192,1000,1,0,\xff\n");
+
+    let error = load_reader(&content[..], BeatmapParseOptions::default()).unwrap_err();
+    assert!(matches!(error.error, OsuParserError::IOError(_)));
+    assert_eq!(error.line_number, Some(23));
+
+    // The broken line is never read if hit objects aren't needed.
+    let options = BeatmapParseOptions {
+        read_editor: false,
+        read_events: false,
+        read_colors: false,
+        read_timing_points: false,
+        read_hit_objects: false,
+        ..Default::default()
+    };
+    let beatmap = load_reader(&content[..], options).unwrap();
+    assert_eq!(beatmap.info.metadata.title, "Title");
+
+    // Reading stops after [Events] too, without waiting for a [Variables] section.
+    let options = BeatmapParseOptions {
+        read_events: true,
+        ..options
+    };
+    let beatmap = load_reader(&content[..], options).unwrap();
+    assert_eq!(beatmap.background_file_name(), Some("bg.jpg"));
+}
+
+#[test]
+pub fn parse_byte_order_mark() {
+    let content = format!(
+        "\u{feff}{}",
+        map_with_objects("[HitObjects]\n256,192,1000,1,0,0:0:0:0:\n")
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.format_version, 14);
+    assert_eq!(beatmap.hit_objects.len(), 1);
+
+    let beatmap = load_reader(content.as_bytes(), BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.format_version, 14);
+}
+
+#[test]
+pub fn parse_borrowed() {
+    let content =
+        std::fs::read_to_string("Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu").unwrap();
+    let beatmap = load_content_ref(&content, BeatmapParseOptions::default()).unwrap();
+    assert!(matches!(beatmap.metadata.title, Cow::Borrowed(_)));
+    assert!(matches!(beatmap.metadata.tags, Cow::Borrowed(_)));
+    assert!(matches!(beatmap.general.audio_file_name, Cow::Borrowed(_)));
+
+    let owned = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.into_owned(), owned);
+
+    let content = map_with_objects(
+        "[Variables]
+$sprite=\"sb/star.png\"
+
+[Events]
+0,0,\"bg.jpg\",0,0
+Sprite,Background,Centre,\"sb/light.png\",320,240
+Sprite,Background,Centre,$sprite,320,240
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:clap.wav
+",
+    );
+    let beatmap = load_content_ref(&content, BeatmapParseOptions::default()).unwrap();
+    match &beatmap.events[..] {
+        [EventRef::Background { filename, .. }] => {
+            assert!(matches!(filename, Cow::Borrowed("bg.jpg")))
+        }
+        events => panic!("expected a background, found {:?}", events),
+    }
+    match &beatmap.storyboard.objects[..] {
+        [StoryboardObjectRef::Sprite(light), StoryboardObjectRef::Sprite(star)] => {
+            assert!(matches!(light.filename, Cow::Borrowed("sb/light.png")));
+            // Expanding the variable makes a new line, which can't be borrowed from.
+            assert!(matches!(&star.filename, Cow::Owned(filename) if filename == "sb/star.png"));
+        }
+        objects => panic!("expected two sprites, found {:?}", objects),
+    }
+    assert!(matches!(
+        beatmap.hit_objects[0].hit_sample.filename,
+        Some(Cow::Borrowed("clap.wav"))
+    ));
+
+    let owned = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.into_owned(), owned);
+}
+
+#[test]
+pub fn parse_mania_hold() {
+    let content = map_with_objects(
+        "[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+448,192,1000,128,0,1500:1:2:0:80:hold.wav
+",
+    )
+    .replace("Mode: 0", "Mode: 3");
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    let key_count = beatmap.info.difficulty.key_count();
+    assert_eq!(key_count, 4);
+
+    let hold = &beatmap.hit_objects[1];
+    assert_eq!(
+        hold.specific,
+        SpecificHitObject::ManiaHold { end_time: 1500.0 }
+    );
+    assert_eq!(hold.hit_sample.normal_set, Some(SampleSet::Normal));
+    assert_eq!(hold.hit_sample.volume, 80);
+    assert_eq!(hold.hit_sample.filename.as_deref(), Some("hold.wav"));
+    assert_eq!(beatmap.hit_objects[0].mania_column(key_count), 0);
+    assert_eq!(hold.mania_column(key_count), 3);
+
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn combo_numbering() {
+    let content = map_with_objects(
+        "[Colours]
+Combo1 : 255,0,0
+Combo2 : 0,255,0
+Combo3 : 0,0,255
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,192,1500,1,0,0:0:0:0:
+256,192,2000,37,0,0:0:0:0:
+256,192,2500,12,0,4000,0:0:0:0:
+256,192,4500,1,0,0:0:0:0:
+256,192,5000,1,0,0:0:0:0:
+",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.hit_objects[2].combo_skip, 2);
+
+    let combos: Vec<_> = beatmap
+        .combos()
+        .iter()
+        .map(|c| (c.combo_number, c.index_in_combo, c.color_index))
+        .collect();
+    // The skip of the third object wraps around to the first colour, and the spinner doesn't
+    // start a combo but makes the object after it start one.
+    assert_eq!(
+        combos,
+        vec![
+            (1, 0, 0),
+            (1, 1, 0),
+            (2, 0, 0),
+            (2, 1, 0),
+            (3, 0, 1),
+            (3, 1, 1)
+        ]
+    );
+    assert_eq!(beatmap.combos()[4].color, ComboColor { r: 0, g: 255, b: 0 });
+
+    // Without a new combo on the spinner, the object after it only starts a combo in v8 and older.
+    let content = content.replace("256,192,2500,12,", "256,192,2500,8,");
+    for (version, after_spinner) in [(14, (2, 2, 0)), (8, (3, 0, 1))] {
+        let content = content.replace("v14", &format!("v{}", version));
+        let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+        assert_eq!(beatmap.format_version, version);
+        let combo = beatmap.combos()[4];
+        assert_eq!(
+            (combo.combo_number, combo.index_in_combo, combo.color_index),
+            after_spinner,
+            "v{}",
+            version
+        );
+    }
+}
+
+#[test]
+pub fn parse_times_and_positions() {
+    let content = map_with_objects(
+        "[Editor]
+Bookmarks: -100.5,2000.75
+
+[Events]
+1,-250,\"video.mp4\",0,0
+2,1000.5,3000.25
+Sprite,Background,TopLeft,\"sprite.png\",320,240
+ F,0,-100.5,200.25,0,1
+Sample,-50.5,0,\"hit.wav\",100
+
+[TimingPoints]
+-23.75,500,4,1,0,100,1,0
+
+[HitObjects]
+256,192,-12.5,1,0,0:0:0:0:
+256,192,1000.125,12,0,2000.75,0:0:0:0:
+-40,600,3000,2,0,L|-80:-20,1,100
+",
+    )
+    .replace(
+        "Mode: 0",
+        "Mode: 0\nAudioLeadIn: 250.5\nPreviewTime: 1500.25",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.timing_points[0].time, -23.75);
+    assert_eq!(beatmap.hit_objects[0].time, -12.5);
+    assert_eq!(
+        beatmap.hit_objects[1].specific,
+        SpecificHitObject::Spinner { end_time: 2000.75 }
+    );
+    assert_eq!(beatmap.events[1], Event::Break(1000.5..3000.25));
+    assert_eq!(beatmap.hit_objects[2].position, osu_point(-40, 600));
+    match &beatmap.events[0] {
+        Event::Video { start_time, .. } => assert_eq!(*start_time, -250.0),
+        event => panic!("expected a video, found {:?}", event),
+    }
+    assert_eq!(beatmap.info.general_data.audio_lead_in, 250.5);
+    assert_eq!(beatmap.info.general_data.preview_time, Some(1500.25));
+    assert_eq!(beatmap.editor.bookmarks, vec![-100.5, 2000.75]);
+    match &beatmap.storyboard.objects[..] {
+        [StoryboardObject::Sprite(sprite), StoryboardObject::Sample(sample)] => {
+            match &sprite.commands[0] {
+                Command::Fade(fade) => {
+                    assert_eq!((fade.start_time, fade.end_time), (-100.5, 200.25))
+                }
+                command => panic!("expected a fade, found {:?}", command),
+            }
+            assert_eq!(sample.time, -50.5);
+        }
+        objects => panic!("expected a sprite and a sample, found {:?}", objects),
+    }
+
+    let content = save_content(&beatmap, BeatmapWriteOptions::default()).unwrap();
+    let reparsed = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap, reparsed);
+}
+
+#[test]
+pub fn parse_events_without_offsets() {
+    let content = map_with_objects(
+        "[Events]
+0,0,\"bg.jpg\"
+Video,0,\"movie.avi\"
+",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(
+        beatmap.events,
+        vec![
+            Event::Background {
+                filename: "bg.jpg".to_owned(),
+                offset: osu_point(0, 0),
+            },
+            Event::Video {
+                start_time: 0.0,
+                filename: "movie.avi".to_owned(),
+                offset: osu_point(0, 0),
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn beatmap_checksum() {
+    let (beatmap, checksum) = load_file_with_checksum(
+        "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu",
+        BeatmapParseOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(beatmap.info.metadata.version, "Extra");
+    assert_eq!(checksum_hex(&checksum), "bf687a174f987ca1850fadcbd831ce15");
+}
+
+#[test]
+pub fn scan_beatmap_header() {
+    let path = "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu";
+    let beatmap = load_file(path, BeatmapParseOptions::default()).unwrap();
+
+    let header = scan_header_file(path, HeaderScanOptions::default()).unwrap();
+    assert_eq!(header.info, beatmap.info);
+    assert_eq!(header.hit_objects, None);
+
+    let options = HeaderScanOptions {
+        count_hit_objects: true,
+        ..Default::default()
+    };
+    let header = scan_header_file(path, options).unwrap();
+    let summary = header.hit_objects.unwrap();
+    assert_eq!(summary.count() as usize, beatmap.hit_objects.len());
+    let sliders = beatmap
+        .hit_objects
+        .iter()
+        .filter(|o| matches!(o.specific, SpecificHitObject::Slider { .. }))
+        .count();
+    assert_eq!(summary.sliders as usize, sliders);
+    assert_eq!(summary.first_time, Some(beatmap.hit_objects[0].time));
+    assert_eq!(
+        summary.last_time,
+        Some(beatmap.hit_objects.last().unwrap().time)
+    );
+}
+
+#[cfg(feature = "index")]
+#[test]
+pub fn index_songs_folder() {
+    use osu_parser::{index_songs, IndexOptions};
+
+    let root = std::env::temp_dir().join(format!("osu-parser-index-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let folder = root.join("1 Mixed");
+    std::fs::create_dir_all(&folder).unwrap();
+    std::fs::create_dir_all(root.join("2 Broken")).unwrap();
+    for name in [
+        "Kenji Ninuma - DISCOüÜPRINCE (peppy) [Normal].osu",
+        "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu",
+        "cYsmix feat. Emmy - Tear Rain (jonathanlfj) [Insane].osu",
+    ] {
+        std::fs::copy(name, folder.join(name)).unwrap();
+    }
+    std::fs::write(root.join("2 Broken/broken.osu"), "not a beatmap").unwrap();
+
+    let index = index_songs(&root, IndexOptions::default());
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(index.beatmap_count(), 3);
+    let set_ids: Vec<_> = index.sets.iter().map(|set| set.beatmap_set_id).collect();
+    assert_eq!(set_ids, vec![None, Some(140662), Some(1471082)]);
+    assert!(index.sets.iter().all(|set| set.folder == folder));
+    assert_eq!(index.errors.len(), 1);
+    assert!(index.errors[0].path.ends_with("broken.osu"));
+}
+
+#[cfg(feature = "osz")]
+#[test]
+pub fn read_osz_archive() {
+    use std::io::{Cursor, Read, Write};
+
+    use osu_parser::OszArchive;
+
+    let beatmap_name = "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu";
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let files: [(&str, &[u8]); 5] = [
+        (beatmap_name, &std::fs::read(beatmap_name).unwrap()),
+        ("Audio.mp3", b"audio"),
+        ("ruuruuruu.png", b"background"),
+        ("soft-hitclap2.wav", b"clap"),
+        ("sb/star.png", b"star"),
+    ];
+    for (name, bytes) in files {
+        writer
+            .start_file(name, zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(bytes).unwrap();
+    }
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let mut archive = OszArchive::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(archive.file_names().count(), 5);
+    assert_eq!(
+        archive.beatmap_file_names().collect::<Vec<_>>(),
+        vec![beatmap_name]
+    );
+    assert_eq!(
+        archive.hitsound_file_names().collect::<Vec<_>>(),
+        vec!["soft-hitclap2.wav"]
+    );
+    assert!(archive.contains("SB\\Star.png"));
+
+    let beatmaps = archive.load_beatmaps(BeatmapParseOptions::default());
+    assert_eq!(beatmaps.len(), 1);
+    let beatmap = beatmaps.into_iter().next().unwrap().1.unwrap();
+    assert_eq!(
+        beatmap,
+        load_file(beatmap_name, BeatmapParseOptions::default()).unwrap()
+    );
+
+    let mut audio = String::new();
+    archive
+        .open_audio(&beatmap)
+        .unwrap()
+        .read_to_string(&mut audio)
+        .unwrap();
+    assert_eq!(audio, "audio");
+    let mut background = Vec::new();
+    archive
+        .open_background(&beatmap)
+        .unwrap()
+        .unwrap()
+        .read_to_end(&mut background)
+        .unwrap();
+    assert_eq!(background, b"background");
+
+    assert!(matches!(
+        archive
+            .open_file("missing.wav")
+            .map(|_| ())
+            .unwrap_err()
+            .error,
+        OsuParserError::IOError(_)
+    ));
+}
+
+#[test]
+pub fn beatmap_set_files() {
+    use osu_parser::{AssetKind, BeatmapSet};
+    use osu_types::osz2::{BeatmapPackage, PackageFile};
+
+    let beatmap_name = "Nakiri Ayame - Good-bye sengen (Mir) [Extra].osu";
+    let root = std::env::temp_dir().join(format!("osu-parser-set-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("SB")).unwrap();
+    std::fs::copy(beatmap_name, root.join(beatmap_name)).unwrap();
+    std::fs::write(
+        root.join("Nakiri Ayame - Good-bye sengen (Mir).osb"),
+        "[Events]\nSprite,Background,Centre,\"sb\\star.png\",320,240\nSample,0,0,\"sb/missing.wav\",100\n",
+    )
+    .unwrap();
+    for name in [
+        "audio.mp3",
+        "old.osb",
+        "RuuRuuRuu.png",
+        "SB/star.png",
+        "soft-hitclap7.wav",
+        "soft-hitclap99.wav",
+        "thumbs.db",
+    ] {
+        std::fs::write(root.join(name), b"").unwrap();
+    }
+
+    let set = BeatmapSet::from_folder(&root, BeatmapParseOptions::default()).unwrap();
+    assert!(set.errors.is_empty());
+    assert_eq!(set.difficulties.len(), 1);
+    assert_eq!(set.difficulties[0].file_name, beatmap_name);
+    assert_eq!(set.storyboard.objects.len(), 2);
+    assert_eq!(
+        set.storyboard_file_name.as_deref(),
+        Some("Nakiri Ayame - Good-bye sengen (Mir).osb")
+    );
+    assert_eq!(set.resolve("sb\\STAR.png"), Some("SB/star.png"));
+
+    let references = set.file_references();
+    let background = references
+        .iter()
+        .find(|reference| reference.kind == AssetKind::Background)
+        .unwrap();
+    assert_eq!(background.file_name, "ruuruuruu.png");
+    assert_eq!(background.resolved.as_deref(), Some("RuuRuuRuu.png"));
+    assert_eq!(background.difficulty, Some(0));
+
+    let missing = set.missing_files();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].file_name, "sb/missing.wav");
+    assert_eq!(missing[0].kind, AssetKind::StoryboardSample);
+    assert_eq!(missing[0].difficulty, None);
+    // Only one storyboard is used, the other one is reported.
+    assert_eq!(
+        set.unused_files(),
+        vec!["old.osb", "soft-hitclap99.wav", "thumbs.db"]
+    );
+
+    let package = BeatmapPackage {
+        metadata: Default::default(),
+        metadata_hash: [0; 16],
+        difficulties: Default::default(),
+        files: set
+            .files
+            .iter()
+            .map(|name| {
+                let file = PackageFile {
+                    length: 0,
+                    hash: [0; 16],
+                };
+                (name.clone(), file)
+            })
+            .collect(),
+    };
+    let from_package = BeatmapSet::from_package(
+        &package,
+        |name| std::fs::read(root.join(name)),
+        BeatmapParseOptions::default(),
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(from_package.files, set.files);
+    assert_eq!(from_package.difficulties, set.difficulties);
+    assert_eq!(from_package.unused_files(), set.unused_files());
+}
+
+#[test]
+pub fn beatmap_stacking() {
+    let content = map_with_objects(
+        "[TimingPoints]
+0,500,4,2,0,100
+
+[HitObjects]
+0,0,1000,2,0,L|100:0,2,100
+100,0,2100,1,0
+",
+    )
+    .replace("Mode: 0", "Mode: 0\nStackLeniency: 0.7");
+    // The slider ends back on its head. Only the legacy algorithm stacks the circle on the end
+    // of its path anyway.
+    let legacy = content.replace("v14", "v5");
+    let beatmap = load_content(&legacy, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.info.general_data.stack_leniency, 0.7);
+    assert_eq!(beatmap.stacking_algorithm(), StackingAlgorithm::Legacy);
+    assert_eq!(beatmap.stack_heights(), vec![0, -1]);
+
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    assert_eq!(beatmap.stacking_algorithm(), StackingAlgorithm::Modern);
+    assert_eq!(beatmap.stack_heights(), vec![0, 0]);
+}
+
+#[test]
+pub fn beatmap_statistics() {
+    let content = map_with_objects(
+        "[Events]
+2,4000,5500
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+6000,250,4,2,0,100,1,0
+
+[HitObjects]
+0,0,1000,1,0,0:0:0:0:
+0,0,1500,2,0,L|200:0,2,200
+256,192,6000,12,0,7000,0:0:0:0:
+0,0,8000,5,0,0:0:0:0:
+",
+    );
+    let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+    let statistics = beatmap.statistics();
+    assert_eq!(statistics.circles, 2);
+    assert_eq!(statistics.sliders, 1);
+    assert_eq!(statistics.spinners, 1);
+    // Circles and the spinner, plus head, two ticks, repeat and tail of the slider.
+    assert_eq!(statistics.max_combo, 8);
+    assert_eq!(statistics.total_length, 7000.0);
+    assert_eq!(statistics.drain_time, 5500.0);
+    assert_eq!(statistics.min_bpm, 120.0);
+    assert_eq!(statistics.max_bpm, 240.0);
+    assert_eq!(statistics.most_common_bpm, 120.0);
+
+    for (mode, max_combo) in [(1, 2), (2, 7), (3, 4)] {
+        let content = content.replace("Mode: 0", &format!("Mode: {}", mode));
+        let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+        assert_eq!(beatmap.statistics().max_combo, max_combo, "mode {}", mode);
+    }
+
+    // Sliders with a broken length still count, with just a head and a tail.
+    for slider in [
+        "0,0,1500,2,0,P|25:25|50:0,1,0",
+        "0,0,1500,2,0,L|100:0,1,-100",
+    ] {
+        let content = content.replace("0,0,1500,2,0,L|200:0,2,200", slider);
+        let beatmap = load_content(&content, BeatmapParseOptions::default()).unwrap();
+        let statistics = beatmap.statistics();
+        assert_eq!(statistics.sliders, 1, "{}", slider);
+        assert_eq!(statistics.max_combo, 5, "{}", slider);
+    }
 }
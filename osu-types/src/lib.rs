@@ -1,4 +1,5 @@
 pub mod osz2;
+pub mod storyboard;
 
 #[cfg(feature = "serde")]
 use serde_crate::{Deserialize, Serialize};
@@ -18,9 +19,11 @@ pub enum BeatmapFileSection {
     TimingPoints,
     Colors,
     HitObjects,
+    Variables,
 }
 
 impl BeatmapFileSection {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         let v = match s {
             "General" => Self::General,
@@ -31,6 +34,7 @@ impl BeatmapFileSection {
             "TimingPoints" => Self::TimingPoints,
             "Colours" => Self::Colors,
             "HitObjects" => Self::HitObjects,
+            "Variables" => Self::Variables,
             _ => return None,
         };
         Some(v)
@@ -62,6 +66,7 @@ pub enum SampleSet {
 }
 
 impl SampleSet {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         let v = match s {
             "Normal" => Self::Normal,
@@ -73,6 +78,33 @@ impl SampleSet {
     }
 }
 
+/// Where hit circle overlays are drawn relative to the numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum OverlayPosition {
+    /// Whatever the skin says.
+    NoChange,
+    Below,
+    Above,
+}
+
+impl OverlayPosition {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let v = match s {
+            "NoChange" => Self::NoChange,
+            "Below" => Self::Below,
+            "Above" => Self::Above,
+            _ => return None,
+        };
+        Some(v)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(
     feature = "serde",
@@ -100,7 +132,7 @@ pub struct RGB<T> {
 
 pub type ComboColor = RGB<u8>;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -109,14 +141,33 @@ pub type ComboColor = RGB<u8>;
 pub enum Event {
     Background {
         filename: String,
-        offset: (u16, u16),
+        offset: OsuPoint,
     },
     Video {
-        start_time: u32,
+        start_time: Time,
         filename: String,
-        offset: (u16, u16),
+        offset: OsuPoint,
+    },
+    Break(std::ops::Range<Time>),
+    /// Background colour change, only used by very old beatmaps.
+    Color {
+        start_time: Time,
+        color: RGB<u8>,
     },
-    Break(std::ops::Range<u32>),
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(
+        feature = "serde",
+        derive(Serialize, Deserialize),
+        serde(crate = "serde_crate", transparent)
+    )]
+    pub struct Effects: u8 {
+        const KIAI = 1 << 0;
+        /// Don't draw the first bar line in osu!taiko and osu!mania.
+        const OMIT_FIRST_BARLINE = 1 << 3;
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -126,14 +177,14 @@ pub enum Event {
     serde(crate = "serde_crate")
 )]
 pub struct TimingPoint {
-    pub time: i32,
+    pub time: Time,
     pub beat_length: f32,
     pub meter: u8,
     pub sample_set: Option<SampleSet>,
     pub sample_index: u8,
     pub volume: u8,
     pub uninherited: bool,
-    pub effects: u8,
+    pub effects: Effects,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -162,25 +213,88 @@ pub enum SpecificHitObject {
         curve_points: Vec<OsuPoint>,
         slides: u8,
         length: f32,
-        edge_sounds: Vec<u8>,
+        /// Hit sounds played on the head, each repeat and the tail.
+        edge_sounds: Vec<HitSound>,
+        /// Sample sets used on the head, each repeat and the tail.
+        edge_sets: Vec<EdgeSet>,
     },
     Spinner {
-        end_time: u32,
+        end_time: Time,
     },
+    /// An osu!mania hold note. Its hit sample is stored in [`HitObject::hit_sample`] like for all
+    /// other objects.
     ManiaHold {
-        // TODO
+        end_time: Time,
     },
 }
 
-#[derive(Debug, Clone, PartialEq)]
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(
+        feature = "serde",
+        derive(Serialize, Deserialize),
+        serde(crate = "serde_crate", transparent)
+    )]
+    pub struct HitSound: u8 {
+        const NORMAL = 1 << 0;
+        const WHISTLE = 1 << 1;
+        const FINISH = 1 << 2;
+        const CLAP = 1 << 3;
+    }
+}
+
+bitflags::bitflags! {
+    /// The type field of a hit object.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(
+        feature = "serde",
+        derive(Serialize, Deserialize),
+        serde(crate = "serde_crate", transparent)
+    )]
+    pub struct HitObjectType: u8 {
+        const CIRCLE = 1 << 0;
+        const SLIDER = 1 << 1;
+        const NEW_COMBO = 1 << 2;
+        const SPINNER = 1 << 3;
+        /// Number of combo colours to skip, see [`HitObjectType::combo_skip`].
+        const COMBO_SKIP = 0b111 << 4;
+        const MANIA_HOLD = 1 << 7;
+    }
+}
+
+impl HitObjectType {
+    pub fn combo_skip(self) -> u8 {
+        (self & Self::COMBO_SKIP).bits() >> 4
+    }
+
+    pub fn with_combo_skip(self, combo_skip: u8) -> Self {
+        (self - Self::COMBO_SKIP) | Self::from_bits_retain((combo_skip & 0b111) << 4)
+    }
+}
+
+/// Sample sets of one edge of a slider. Sets that are `None` come from the slider's hit sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct EdgeSet {
+    pub normal_set: Option<SampleSet>,
+    pub addition_set: Option<SampleSet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
     serde(crate = "serde_crate")
 )]
 pub struct HitSample {
-    pub normal_set: u8,
-    pub addition_set: u8,
+    /// `None` uses the sample set of the active timing point.
+    pub normal_set: Option<SampleSet>,
+    /// `None` uses the normal set.
+    pub addition_set: Option<SampleSet>,
     pub index: u8,
     pub volume: u8,
     pub filename: Option<String>,
@@ -193,12 +307,40 @@ pub struct HitSample {
     serde(crate = "serde_crate")
 )]
 pub struct HitObject {
-    pub position: (u16, u16),
-    pub time: u32,
+    /// Position in osu!pixels. Objects can be placed outside of the playfield.
+    pub position: OsuPoint,
+    pub time: Time,
     pub specific: SpecificHitObject,
-    pub hit_sound: u8,
+    pub hit_sound: HitSound,
     pub hit_sample: HitSample,
     pub new_combo: bool,
+    /// Number of combo colours to skip when this object starts a new combo.
+    pub combo_skip: u8,
+}
+
+impl HitObject {
+    /// The type field that represents the object in a .osu file.
+    pub fn object_type(&self) -> HitObjectType {
+        let ty = match self.specific {
+            SpecificHitObject::Circle => HitObjectType::CIRCLE,
+            SpecificHitObject::Slider { .. } => HitObjectType::SLIDER,
+            SpecificHitObject::Spinner { .. } => HitObjectType::SPINNER,
+            SpecificHitObject::ManiaHold { .. } => HitObjectType::MANIA_HOLD,
+        };
+        let ty = ty.with_combo_skip(self.combo_skip);
+        if self.new_combo {
+            ty | HitObjectType::NEW_COMBO
+        } else {
+            ty
+        }
+    }
+
+    /// The osu!mania column of the object, counting from 0. The x position is spread evenly over
+    /// the `key_count` columns.
+    pub fn mania_column(&self, key_count: u8) -> u8 {
+        let column = (self.position.x as f32 * key_count as f32 / 512.0).floor();
+        (column as u8).min(key_count.saturating_sub(1))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -209,14 +351,60 @@ pub struct HitObject {
 )]
 pub struct BeatmapGeneralData {
     pub audio_file_name: String,
-    pub audio_lead_in: u32,
-    pub preview_time: u32,
+    pub audio_lead_in: Time,
+    /// Where the song select preview starts, `None` if the beatmap doesn't set one.
+    pub preview_time: Option<Time>,
     pub countdown: Option<Countdown>,
     pub sample_set: SampleSet,
     pub stack_leniency: f32,
     pub mode: Mode,
     pub letterbox_in_breaks: bool,
+    /// Whether the storyboard is drawn in front of combo fire.
+    pub story_fire_in_front: bool,
+    /// Whether the storyboard may use sprites from the player's skin.
+    pub use_skin_sprites: bool,
+    /// Deprecated.
+    pub always_show_playfield: bool,
+    pub overlay_position: OverlayPosition,
+    /// Preferred skin to use during gameplay.
+    pub skin_preference: Option<String>,
+    pub epilepsy_warning: bool,
+    /// Time in beats the countdown starts before the first hit object.
+    pub countdown_offset: u32,
+    /// N+1 key layout in osu!mania.
+    pub special_style: bool,
     pub widescreen_storyboard: bool,
+    /// Whether sound samples change speed with rate changing mods.
+    pub samples_match_playback_rate: bool,
+    /// Deprecated.
+    pub audio_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct BeatmapEditor {
+    pub bookmarks: Vec<Time>,
+    /// Multiplier for the distance snap.
+    pub distance_spacing: f32,
+    pub beat_divisor: u8,
+    pub grid_size: u8,
+    pub timeline_zoom: f32,
+}
+
+impl Default for BeatmapEditor {
+    fn default() -> Self {
+        Self {
+            bookmarks: Vec::new(),
+            distance_spacing: 1.0,
+            beat_divisor: 4,
+            grid_size: 4,
+            timeline_zoom: 1.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -253,6 +441,13 @@ pub struct BeatmapDifficulty {
     pub slider_tick_rate: f32,
 }
 
+impl BeatmapDifficulty {
+    /// Number of columns in osu!mania, which is stored as the circle size.
+    pub fn key_count(&self) -> u8 {
+        self.cs.round().max(1.0) as u8
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(
     feature = "serde",
@@ -265,6 +460,10 @@ pub struct BeatmapInfo {
     pub difficulty: BeatmapDifficulty,
 }
 
+/// A point in time in milliseconds. Times can be before the start of the audio and don't have
+/// to be whole milliseconds.
+pub type Time = f64;
+
 pub type OsuPoint = mint::Point2<i16>;
 
 pub fn osu_point(x: i16, y: i16) -> OsuPoint {
@@ -0,0 +1,320 @@
+#[cfg(feature = "serde")]
+use serde_crate::{Deserialize, Serialize};
+
+use crate::{Time, RGB};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, num_enum::TryFromPrimitive)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[repr(u8)]
+pub enum Layer {
+    Background = 0,
+    Fail = 1,
+    Pass = 2,
+    Foreground = 3,
+    Overlay = 4,
+}
+
+impl Layer {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let v = match s {
+            "Background" => Self::Background,
+            "Fail" => Self::Fail,
+            "Pass" => Self::Pass,
+            "Foreground" => Self::Foreground,
+            "Overlay" => Self::Overlay,
+            _ => return None,
+        };
+        Some(v)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, num_enum::TryFromPrimitive)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[repr(u8)]
+pub enum Origin {
+    TopLeft = 0,
+    Centre = 1,
+    CentreLeft = 2,
+    TopRight = 3,
+    BottomCentre = 4,
+    TopCentre = 5,
+    Custom = 6,
+    CentreRight = 7,
+    BottomLeft = 8,
+    BottomRight = 9,
+}
+
+impl Origin {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let v = match s {
+            "TopLeft" => Self::TopLeft,
+            "Centre" => Self::Centre,
+            "CentreLeft" => Self::CentreLeft,
+            "TopRight" => Self::TopRight,
+            "BottomCentre" => Self::BottomCentre,
+            "TopCentre" => Self::TopCentre,
+            "Custom" => Self::Custom,
+            "CentreRight" => Self::CentreRight,
+            "BottomLeft" => Self::BottomLeft,
+            "BottomRight" => Self::BottomRight,
+            _ => return None,
+        };
+        Some(v)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, num_enum::TryFromPrimitive)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[repr(u8)]
+pub enum LoopType {
+    LoopForever = 0,
+    LoopOnce = 1,
+}
+
+impl LoopType {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let v = match s {
+            "LoopForever" => Self::LoopForever,
+            "LoopOnce" => Self::LoopOnce,
+            _ => return None,
+        };
+        Some(v)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, num_enum::TryFromPrimitive)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[repr(u8)]
+pub enum Easing {
+    Linear = 0,
+    EasingOut = 1,
+    EasingIn = 2,
+    QuadIn = 3,
+    QuadOut = 4,
+    QuadInOut = 5,
+    CubicIn = 6,
+    CubicOut = 7,
+    CubicInOut = 8,
+    QuartIn = 9,
+    QuartOut = 10,
+    QuartInOut = 11,
+    QuintIn = 12,
+    QuintOut = 13,
+    QuintInOut = 14,
+    SineIn = 15,
+    SineOut = 16,
+    SineInOut = 17,
+    ExpoIn = 18,
+    ExpoOut = 19,
+    ExpoInOut = 20,
+    CircIn = 21,
+    CircOut = 22,
+    CircInOut = 23,
+    ElasticIn = 24,
+    ElasticOut = 25,
+    ElasticHalfOut = 26,
+    ElasticQuarterOut = 27,
+    ElasticInOut = 28,
+    BackIn = 29,
+    BackOut = 30,
+    BackInOut = 31,
+    BounceIn = 32,
+    BounceOut = 33,
+    BounceInOut = 34,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum Parameter {
+    FlipHorizontal,
+    FlipVertical,
+    AdditiveBlend,
+}
+
+/// A value changing from `start` to `end` over the time span of a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Transform<T> {
+    pub easing: Easing,
+    pub start_time: Time,
+    pub end_time: Time,
+    pub start: T,
+    pub end: T,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum Command {
+    Fade(Transform<f32>),
+    Move(Transform<mint::Point2<f32>>),
+    MoveX(Transform<f32>),
+    MoveY(Transform<f32>),
+    Scale(Transform<f32>),
+    VectorScale(Transform<mint::Vector2<f32>>),
+    Rotate(Transform<f32>),
+    Color(Transform<RGB<u8>>),
+    Parameter {
+        easing: Easing,
+        start_time: Time,
+        end_time: Time,
+        parameter: Parameter,
+    },
+    /// Repeats the contained commands `loop_count` times. The times of the contained commands are
+    /// relative to `start_time`.
+    Loop {
+        start_time: Time,
+        loop_count: u32,
+        commands: Vec<Command>,
+    },
+    /// Runs the contained commands when `trigger` fires between `start_time` and `end_time`.
+    Trigger {
+        trigger: String,
+        start_time: Time,
+        end_time: Time,
+        group: Option<i32>,
+        commands: Vec<Command>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Sprite {
+    pub layer: Layer,
+    pub origin: Origin,
+    pub filename: String,
+    pub position: mint::Point2<f32>,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Animation {
+    pub layer: Layer,
+    pub origin: Origin,
+    /// Path of the frames, the frame index is inserted before the extension.
+    pub filename: String,
+    pub position: mint::Point2<f32>,
+    pub frame_count: u32,
+    pub frame_delay: f32,
+    pub loop_type: LoopType,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Sample {
+    pub time: Time,
+    pub layer: Layer,
+    pub filename: String,
+    pub volume: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum StoryboardObject {
+    Sprite(Sprite),
+    Animation(Animation),
+    Sample(Sample),
+}
+
+impl StoryboardObject {
+    pub fn layer(&self) -> Layer {
+        match self {
+            StoryboardObject::Sprite(sprite) => sprite.layer,
+            StoryboardObject::Animation(animation) => animation.layer,
+            StoryboardObject::Sample(sample) => sample.layer,
+        }
+    }
+
+    /// The commands of the object, samples don't have any.
+    pub fn commands(&self) -> &[Command] {
+        match self {
+            StoryboardObject::Sprite(sprite) => &sprite.commands,
+            StoryboardObject::Animation(animation) => &animation.commands,
+            StoryboardObject::Sample(_) => &[],
+        }
+    }
+}
+
+/// Storyboard objects in the order they are defined in. Objects on the same layer are drawn in
+/// this order.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Storyboard {
+    pub objects: Vec<StoryboardObject>,
+}
+
+impl Storyboard {
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn layer(&self, layer: Layer) -> impl Iterator<Item = &StoryboardObject> {
+        self.objects.iter().filter(move |o| o.layer() == layer)
+    }
+
+    /// Combines the storyboard shared by a beatmapset (the .osb file) with the storyboard of a
+    /// single difficulty. Like in the client, the difficulty's objects are drawn on top of the
+    /// shared objects in the same layer.
+    pub fn merge(&self, difficulty: &Storyboard) -> Storyboard {
+        Storyboard {
+            objects: self
+                .objects
+                .iter()
+                .chain(difficulty.objects.iter())
+                .cloned()
+                .collect(),
+        }
+    }
+}
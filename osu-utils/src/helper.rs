@@ -56,7 +56,7 @@ impl MintPointExt for mint::Point2<f32> {
     }
 
     fn distance(self, b: mint::Point2<f32>) -> f32 {
-        ((self.x - b.x).abs().powi(2) + (self.y - b.y).abs().powi(2)).sqrt()
+        self.sub(b).length()
     }
 }
 
@@ -1,7 +1,13 @@
 mod helper;
+mod slider;
 mod spline;
+mod stacking;
+mod timing;
 
+pub use slider::*;
 pub use spline::*;
+pub use stacking::*;
+pub use timing::*;
 
 fn difficulty_range(difficulty: f32, min: f32, mid: f32, max: f32) -> f32 {
     if difficulty > 5.0 {
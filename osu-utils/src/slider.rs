@@ -0,0 +1,212 @@
+use osu_types::{BeatmapDifficulty, HitObject, SpecificHitObject, Time};
+
+use crate::{Spline, TimingIndex};
+
+/// Slider length that is covered by one beat at 1x slider velocity and a slider multiplier of 1.
+pub const BASE_SCORING_DISTANCE: f64 = 100.0;
+
+/// The client judges the end of a slider this long before it actually ends.
+pub const LEGACY_LAST_TICK_OFFSET: f64 = 36.0;
+
+/// Ticks closer than this to the end of a span are dropped.
+const MIN_TICK_DISTANCE_FROM_END: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliderEventKind {
+    Head,
+    Tick,
+    Repeat,
+    /// The end of the slider as the client judges it, [`LEGACY_LAST_TICK_OFFSET`] before the
+    /// slider ends, but not earlier than halfway through the slider.
+    Tail,
+}
+
+/// An object nested in a slider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliderEvent {
+    pub kind: SliderEventKind,
+    pub time: Time,
+    /// The span the event is in, counting from 0.
+    pub span: u32,
+    /// Where on the path the event is, from 0 at the head to 1 at the end of the path.
+    pub path_progress: f64,
+    pub position: mint::Point2<f32>,
+}
+
+/// Timing and shape of a slider.
+#[derive(Debug, Clone)]
+pub struct Slider {
+    pub start_time: Time,
+    pub end_time: Time,
+    /// Number of times the path is travelled, one more than the number of repeats.
+    pub span_count: u32,
+    pub span_duration: f64,
+    /// Velocity in osu!pixels per millisecond.
+    pub velocity: f64,
+    pub tick_distance: f64,
+    /// The path, fitted to the pixel length of the slider.
+    pub spline: Spline,
+    /// Head, ticks, repeats and tail in chronological order.
+    pub nested: Vec<SliderEvent>,
+}
+
+impl Slider {
+    /// Returns `None` if the object is not a slider.
+    pub fn new(
+        object: &HitObject,
+        difficulty: &BeatmapDifficulty,
+        timing: &TimingIndex,
+    ) -> Option<Self> {
+        let (curve_type, curve_points, slides, length) = match &object.specific {
+            SpecificHitObject::Slider {
+                curve_type,
+                curve_points,
+                slides,
+                length,
+                ..
+            } => (*curve_type, curve_points, *slides, *length),
+            _ => return None,
+        };
+
+        let mut control_points = Vec::with_capacity(curve_points.len() + 1);
+        control_points.push(object.position);
+        control_points.extend_from_slice(curve_points);
+        // Like the client, a length that isn't positive (or isn't a number) means the slider
+        // follows its whole path.
+        let expected_length = Some(length).filter(|length| *length > 0.0);
+        let mut spline = Spline::from_control(curve_type, &control_points, expected_length);
+        let length = match expected_length {
+            Some(length) => {
+                spline.fit_to_length(length);
+                length
+            }
+            None => spline.pixel_length(),
+        };
+
+        let state = timing.state_at(object.time);
+        let scoring_distance =
+            BASE_SCORING_DISTANCE * f64::from(difficulty.slider_multiplier) * state.slider_velocity;
+        let velocity = scoring_distance / state.beat_length;
+        let tick_distance = scoring_distance / f64::from(difficulty.slider_tick_rate);
+
+        let span_count = u32::from(slides.max(1));
+        let length = f64::from(length);
+        let span_duration = length / velocity;
+
+        let mut slider = Slider {
+            start_time: object.time,
+            end_time: object.time + span_duration * f64::from(span_count),
+            span_count,
+            span_duration,
+            velocity,
+            tick_distance,
+            spline,
+            nested: Vec::new(),
+        };
+        slider.nested = slider.generate_nested(length);
+        Some(slider)
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+
+    /// Start and end time of each span.
+    pub fn spans(&self) -> impl Iterator<Item = (Time, Time)> + '_ {
+        (0..self.span_count).map(move |span| {
+            let start = self.start_time + f64::from(span) * self.span_duration;
+            (start, start + self.span_duration)
+        })
+    }
+
+    /// Position on the path, from 0 at the head to 1 at the end.
+    pub fn position_at_progress(&self, path_progress: f64) -> mint::Point2<f32> {
+        let length = self.spline.pixel_length();
+        self.spline
+            .point_at_length((path_progress as f32 * length).clamp(0.0, length))
+    }
+
+    /// Where the slider ball is at `time`.
+    pub fn position_at(&self, time: Time) -> mint::Point2<f32> {
+        if self.span_duration <= 0.0 {
+            return self.position_at_progress(0.0);
+        }
+        let progress =
+            ((time - self.start_time) / self.span_duration).clamp(0.0, f64::from(self.span_count));
+        let span = (progress.floor() as u32).min(self.span_count - 1);
+        let span_progress = progress - f64::from(span);
+        if span % 2 == 1 {
+            self.position_at_progress(1.0 - span_progress)
+        } else {
+            self.position_at_progress(span_progress)
+        }
+    }
+
+    fn generate_nested(&self, length: f64) -> Vec<SliderEvent> {
+        let event = |kind, time, span, path_progress| SliderEvent {
+            kind,
+            time,
+            span,
+            path_progress,
+            position: self.position_at_progress(path_progress),
+        };
+
+        let mut nested = vec![event(SliderEventKind::Head, self.start_time, 0, 0.0)];
+
+        // Same limits as the client, so broken maps don't produce endless ticks.
+        let length = length.min(100_000.0);
+        let tick_distance = self.tick_distance.clamp(0.0, length);
+        let min_distance_from_end = self.velocity * MIN_TICK_DISTANCE_FROM_END;
+
+        for span in 0..self.span_count {
+            let span_start = self.start_time + f64::from(span) * self.span_duration;
+            let reversed = span % 2 == 1;
+
+            let mut ticks = Vec::new();
+            if tick_distance > 0.0 {
+                let mut distance = tick_distance;
+                while distance <= length && distance < length - min_distance_from_end {
+                    let path_progress = distance / length;
+                    let time_progress = if reversed {
+                        1.0 - path_progress
+                    } else {
+                        path_progress
+                    };
+                    let time = span_start + time_progress * self.span_duration;
+                    ticks.push(event(SliderEventKind::Tick, time, span, path_progress));
+                    distance += tick_distance;
+                }
+            }
+            if reversed {
+                ticks.reverse();
+            }
+            nested.extend(ticks);
+
+            if span + 1 < self.span_count {
+                let path_progress = f64::from((span + 1) % 2);
+                let time = span_start + self.span_duration;
+                nested.push(event(SliderEventKind::Repeat, time, span, path_progress));
+            }
+        }
+
+        let last_span = self.span_count - 1;
+        let last_span_start = self.start_time + f64::from(last_span) * self.span_duration;
+        let tail_time =
+            (self.start_time + self.duration() / 2.0).max(self.end_time - LEGACY_LAST_TICK_OFFSET);
+        let mut tail_progress = if self.span_duration > 0.0 {
+            ((tail_time - last_span_start) / self.span_duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if self.span_count.is_multiple_of(2) {
+            tail_progress = 1.0 - tail_progress;
+        }
+        nested.push(event(
+            SliderEventKind::Tail,
+            tail_time,
+            last_span,
+            tail_progress,
+        ));
+        nested
+    }
+}
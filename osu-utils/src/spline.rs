@@ -27,6 +27,10 @@ impl Spline {
         // no matter what, if there's 2 control points, it's linear
         let mut kind = kind;
         let mut control_points = control_points.to_vec();
+        if control_points.len() == 1 {
+            // a slider without curve points stays on its head
+            control_points.push(control_points[0]);
+        }
         if control_points.len() == 2 {
             kind = CurveType::Linear;
         }
@@ -40,16 +44,20 @@ impl Spline {
             kind = CurveType::Linear;
             control_points.remove(1);
         }
+        // like the client, perfect curves need exactly 3 points
+        if kind == CurveType::Perfect && control_points.len() != 3 {
+            kind = CurveType::Bezier;
+        }
 
         let points = control_points
             .iter()
             .map(|p| p.to_float())
             .collect::<Vec<_>>();
         let spline_points = match kind {
+            CurveType::Linear if points.len() > 2 => points,
             CurveType::Linear => {
-                assert_eq!(points.len(), 2);
                 let start = points[0];
-                let end = if let Some(pixel_length) = pixel_length {
+                let end = if let (Some(pixel_length), false) = (pixel_length, start == points[1]) {
                     point_on_line(points[0], points[1], pixel_length)
                 } else {
                     points[1]
@@ -96,10 +104,14 @@ impl Spline {
 
                     t += (new_t1 - t0) / pixel_length;
                 }
+                // a zero length arc still starts at the head
+                if c.is_empty() {
+                    c.push(p1);
+                }
                 c
             }
             CurveType::Bezier => {
-                assert!(points.len() > 3);
+                assert!(points.len() >= 2);
                 let mut output = Vec::new();
                 let mut last_index = 0;
                 let mut i = 0;
@@ -108,8 +120,8 @@ impl Spline {
                     if multipart_segment || i == points.len() - 1 {
                         let sub = &points[last_index..i + 1];
                         if sub.len() == 2 {
-                            output.push(points[0]);
-                            output.push(points[1]);
+                            output.push(sub[0]);
+                            output.push(sub[1]);
                         } else {
                             create_singlebezier(&mut output, sub);
                         }
@@ -122,7 +134,7 @@ impl Spline {
                 }
                 output
             }
-            _ => todo!(),
+            CurveType::Catmull => create_catmull(&points),
         };
 
         let mut cumulative_lengths = Vec::with_capacity(spline_points.len());
@@ -171,6 +183,27 @@ impl Spline {
         self.cumulative_lengths.truncate(limit_idx + 1);
     }
 
+    /// Make the spline exactly `length` long, like the client does with the pixel length of a
+    /// slider. A spline that is too short is extended in the direction of its last segment.
+    pub fn fit_to_length(&mut self, length: f32) {
+        let current = self.pixel_length();
+        if current > length {
+            self.truncate(length);
+            return;
+        }
+
+        let n = self.spline_points.len();
+        if n < 2 || current == length {
+            return;
+        }
+        let (a, b) = (self.spline_points[n - 2], self.spline_points[n - 1]);
+        let segment = a.distance(b);
+        if segment > 0.0 {
+            self.spline_points[n - 1] = point_on_line(a, b, segment + length - current);
+            self.cumulative_lengths[n - 1] = NotNan::new(length).unwrap();
+        }
+    }
+
     /// Return the pixel length of this spline
     pub fn pixel_length(&self) -> f32 {
         self.cumulative_lengths.last().unwrap().into_inner()
@@ -195,7 +228,10 @@ impl Spline {
     /// Calculate the point at which the slider ball would be after it has traveled a distance of
     /// `length` into the slider.
     pub fn point_at_length(&self, length: f32) -> P {
-        let length_notnan = NotNan::new(length).unwrap();
+        let length_notnan = match NotNan::new(length) {
+            Ok(length) => length,
+            Err(_) => return self.spline_points[0],
+        };
         match self.cumulative_lengths.binary_search(&length_notnan) {
             Ok(idx) => self.spline_points[idx],
             Err(idx) => {
@@ -244,7 +280,7 @@ fn approximate(
 ) {
     let count = control_points.len();
 
-    subdivide(&control_points, l_buf, r_buf, midpoints_buf);
+    subdivide(control_points, l_buf, r_buf, midpoints_buf);
 
     l_buf[count..(count * 2) - 1].clone_from_slice(&r_buf[1..count]);
 
@@ -276,6 +312,43 @@ fn is_flat_enough(control_points: &[P], tolerance_sq: f32) -> bool {
     true
 }
 
+fn create_catmull(points: &[P]) -> Vec<P> {
+    const DETAIL: usize = 50;
+
+    let mut output = Vec::with_capacity((points.len() - 1) * DETAIL + 1);
+    for i in 0..points.len() - 1 {
+        let v1 = if i > 0 { points[i - 1] } else { points[i] };
+        let v2 = points[i];
+        let v3 = points[i + 1];
+        let v4 = if i + 2 < points.len() {
+            points[i + 2]
+        } else {
+            v3.mul(P::new(2.0, 2.0)).sub(v2)
+        };
+
+        for c in 0..DETAIL {
+            output.push(catmull_point(v1, v2, v3, v4, c as f32 / DETAIL as f32));
+        }
+    }
+    output.push(points[points.len() - 1]);
+    output
+}
+
+fn catmull_point(v1: P, v2: P, v3: P, v4: P, t: f32) -> P {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let component = |a: f32, b: f32, c: f32, d: f32| {
+        0.5 * (2.0 * b
+            + (-a + c) * t
+            + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+            + (-a + 3.0 * b - 3.0 * c + d) * t3)
+    };
+    P::new(
+        component(v1.x, v2.x, v3.x, v4.x),
+        component(v1.y, v2.y, v3.y, v4.y),
+    )
+}
+
 fn create_singlebezier(output: &mut Vec<P>, control_points: &[P]) {
     let count = control_points.len();
     const TOLERANCE: f32 = 0.25;
@@ -297,8 +370,7 @@ fn create_singlebezier(output: &mut Vec<P>, control_points: &[P]) {
     let mut r_buf = vec![P::new(0.0, 0.0); count];
     let mut midpoints_buf = vec![P::new(0.0, 0.0); count];
 
-    while !to_flatten.is_empty() {
-        let mut parent = to_flatten.pop().unwrap();
+    while let Some(mut parent) = to_flatten.pop() {
         if is_flat_enough(&parent, TOLERANCE_SQ) {
             approximate(
                 &parent,
@@ -0,0 +1,198 @@
+use osu_types::{BeatmapDifficulty, HitObject, OsuPoint, SpecificHitObject, Time};
+
+use crate::{ar_to_ms, cs_to_px, helper::*, Slider, TimingIndex};
+
+/// Objects closer than this (in osu!pixels) stack on each other.
+const STACK_DISTANCE: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackingAlgorithm {
+    /// Used for beatmaps older than file format v6. It doesn't look at where sliders end.
+    Legacy,
+    Modern,
+}
+
+impl StackingAlgorithm {
+    pub fn for_format_version(version: u8) -> Self {
+        if version < 6 {
+            StackingAlgorithm::Legacy
+        } else {
+            StackingAlgorithm::Modern
+        }
+    }
+}
+
+/// What stacking needs to know about an object.
+struct StackObject {
+    is_slider: bool,
+    is_spinner: bool,
+    start_time: Time,
+    end_time: Time,
+    position: mint::Point2<f32>,
+    /// Where the object ends, for sliders with an even number of spans that is the head.
+    end_position: mint::Point2<f32>,
+    /// End of the slider path, regardless of repeats.
+    path_end: mint::Point2<f32>,
+}
+
+/// Computes how many steps each osu!standard object is moved up and left (or, if negative, down
+/// and right) because it stacks on nearby objects, like the client does.
+///
+/// `objects` have to be sorted by time. The result has one height per object.
+pub fn stack_heights(
+    objects: &[HitObject],
+    difficulty: &BeatmapDifficulty,
+    timing: &TimingIndex,
+    stack_leniency: f32,
+    algorithm: StackingAlgorithm,
+) -> Vec<i32> {
+    let objects: Vec<_> = objects
+        .iter()
+        .map(|object| stack_object(object, difficulty, timing))
+        .collect();
+    let stack_threshold = f64::from(ar_to_ms(difficulty.ar) * stack_leniency);
+
+    let mut heights = vec![0; objects.len()];
+    match algorithm {
+        StackingAlgorithm::Legacy => stack_legacy(&objects, stack_threshold, &mut heights),
+        StackingAlgorithm::Modern => stack_modern(&objects, stack_threshold, &mut heights),
+    }
+    heights
+}
+
+/// How far an object with the given stack height is moved, which grows with the circle size.
+pub fn stack_offset(stack_height: i32, cs: f32) -> mint::Vector2<f32> {
+    let offset = stack_height as f32 * -cs_to_px(cs) / 10.0;
+    mint::Vector2 {
+        x: offset,
+        y: offset,
+    }
+}
+
+/// The position an object is drawn at after stacking.
+pub fn stacked_position(position: OsuPoint, stack_height: i32, cs: f32) -> mint::Point2<f32> {
+    let offset = stack_offset(stack_height, cs);
+    mint::Point2 {
+        x: f32::from(position.x) + offset.x,
+        y: f32::from(position.y) + offset.y,
+    }
+}
+
+fn stack_object(
+    object: &HitObject,
+    difficulty: &BeatmapDifficulty,
+    timing: &TimingIndex,
+) -> StackObject {
+    let position = object.position.to_float();
+    let mut stack_object = StackObject {
+        is_slider: false,
+        is_spinner: false,
+        start_time: object.time,
+        end_time: object.time,
+        position,
+        end_position: position,
+        path_end: position,
+    };
+    match &object.specific {
+        SpecificHitObject::Circle => {}
+        SpecificHitObject::Slider { .. } => {
+            if let Some(slider) = Slider::new(object, difficulty, timing) {
+                stack_object.is_slider = true;
+                stack_object.end_time = slider.end_time;
+                stack_object.end_position = slider.position_at(slider.end_time);
+                stack_object.path_end = slider.position_at_progress(1.0);
+            }
+        }
+        SpecificHitObject::Spinner { end_time } | SpecificHitObject::ManiaHold { end_time } => {
+            stack_object.is_spinner = true;
+            stack_object.end_time = *end_time;
+        }
+    }
+    stack_object
+}
+
+fn stack_modern(objects: &[StackObject], stack_threshold: f64, heights: &mut [i32]) {
+    let mut extended_start = 0;
+    for i in (1..objects.len()).rev() {
+        let mut object_i = i;
+        if heights[i] != 0 || objects[i].is_spinner {
+            continue;
+        }
+
+        if !objects[i].is_slider {
+            for n in (0..i).rev() {
+                let object_n = &objects[n];
+                if object_n.is_spinner {
+                    continue;
+                }
+                if objects[object_i].start_time - object_n.end_time > stack_threshold {
+                    break;
+                }
+                if n < extended_start {
+                    heights[n] = 0;
+                    extended_start = n;
+                }
+
+                // A slider that ends on the stack pushes the following objects down and right.
+                if object_n.is_slider
+                    && object_n.end_position.distance(objects[object_i].position) < STACK_DISTANCE
+                {
+                    let offset = heights[object_i] - heights[n] + 1;
+                    for j in n + 1..=i {
+                        if object_n.end_position.distance(objects[j].position) < STACK_DISTANCE {
+                            heights[j] -= offset;
+                        }
+                    }
+                    break;
+                }
+
+                if object_n.position.distance(objects[object_i].position) < STACK_DISTANCE {
+                    heights[n] = heights[object_i] + 1;
+                    object_i = n;
+                }
+            }
+        } else {
+            for n in (0..i).rev() {
+                let object_n = &objects[n];
+                if object_n.is_spinner {
+                    continue;
+                }
+                if objects[object_i].start_time - object_n.start_time > stack_threshold {
+                    break;
+                }
+                if object_n.end_position.distance(objects[object_i].position) < STACK_DISTANCE {
+                    heights[n] = heights[object_i] + 1;
+                    object_i = n;
+                }
+            }
+        }
+    }
+}
+
+fn stack_legacy(objects: &[StackObject], stack_threshold: f64, heights: &mut [i32]) {
+    for i in 0..objects.len() {
+        let current = &objects[i];
+        if heights[i] != 0 && !current.is_slider {
+            continue;
+        }
+
+        let mut start_time = current.end_time;
+        let mut slider_stack = 0;
+        for j in i + 1..objects.len() {
+            if objects[j].start_time - stack_threshold > start_time {
+                break;
+            }
+
+            // The client uses the start time of the stacked object here, even for sliders.
+            if objects[j].position.distance(current.position) < STACK_DISTANCE {
+                heights[i] += 1;
+                start_time = objects[j].start_time;
+            } else if objects[j].position.distance(current.path_end) < STACK_DISTANCE {
+                // Objects on the end of a slider are moved down and right instead.
+                slider_stack += 1;
+                heights[j] -= slider_stack;
+                start_time = objects[j].start_time;
+            }
+        }
+    }
+}
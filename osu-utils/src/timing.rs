@@ -0,0 +1,129 @@
+use osu_types::{Effects, SampleSet, Time, TimingPoint};
+
+/// Beat length used before the first timing point, or if there are none (60 BPM).
+pub const DEFAULT_BEAT_LENGTH: f64 = 1000.0;
+
+/// Everything the timing points say about a moment in a beatmap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingState {
+    /// Duration of a beat in milliseconds, from the active uninherited (red) timing point.
+    pub beat_length: f64,
+    /// Beats per measure, from the active uninherited timing point.
+    pub meter: u8,
+    /// Slider velocity multiplier, from the active inherited (green) timing point. It is 1 when an
+    /// uninherited point is the most recent.
+    pub slider_velocity: f64,
+    /// `None` means the beatmap's default sample set.
+    pub sample_set: Option<SampleSet>,
+    pub sample_index: u8,
+    pub volume: u8,
+    pub kiai: bool,
+}
+
+impl TimingState {
+    pub fn bpm(&self) -> f64 {
+        60_000.0 / self.beat_length
+    }
+}
+
+impl Default for TimingState {
+    fn default() -> Self {
+        TimingState {
+            beat_length: DEFAULT_BEAT_LENGTH,
+            meter: 4,
+            slider_velocity: 1.0,
+            sample_set: None,
+            sample_index: 0,
+            volume: 100,
+            kiai: false,
+        }
+    }
+}
+
+/// Timing points sorted for lookups by time, which take O(log n).
+///
+/// Like in the client, a query before the first uninherited timing point uses the first one, and
+/// a query before the first timing point uses the first timing point for the samples.
+#[derive(Debug, Clone)]
+pub struct TimingIndex {
+    /// All timing points, sorted by time. Points at the same time keep their order from the file.
+    points: Vec<TimingPoint>,
+    /// Indices of the uninherited points in `points`.
+    uninherited: Vec<usize>,
+}
+
+impl TimingIndex {
+    pub fn new(timing_points: &[TimingPoint]) -> Self {
+        let mut points = timing_points.to_vec();
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let uninherited = points
+            .iter()
+            .enumerate()
+            .filter(|(_, point)| point.uninherited)
+            .map(|(i, _)| i)
+            .collect();
+        TimingIndex {
+            points,
+            uninherited,
+        }
+    }
+
+    /// The timing points, sorted by time.
+    pub fn points(&self) -> &[TimingPoint] {
+        &self.points
+    }
+
+    /// The most recent timing point of either kind.
+    pub fn point_at(&self, time: Time) -> Option<&TimingPoint> {
+        let active = self.points.partition_point(|point| point.time <= time);
+        self.points.get(active.saturating_sub(1))
+    }
+
+    /// The most recent uninherited (red) timing point.
+    pub fn uninherited_at(&self, time: Time) -> Option<&TimingPoint> {
+        let active = self
+            .uninherited
+            .partition_point(|&i| self.points[i].time <= time);
+        let index = self.uninherited.get(active.saturating_sub(1))?;
+        Some(&self.points[*index])
+    }
+
+    pub fn beat_length_at(&self, time: Time) -> f64 {
+        self.uninherited_at(time)
+            .map_or(DEFAULT_BEAT_LENGTH, |point| f64::from(point.beat_length))
+    }
+
+    pub fn slider_velocity_at(&self, time: Time) -> f64 {
+        self.point_at(time).map_or(1.0, slider_velocity)
+    }
+
+    pub fn kiai_at(&self, time: Time) -> bool {
+        self.point_at(time)
+            .is_some_and(|point| point.effects.contains(Effects::KIAI))
+    }
+
+    pub fn state_at(&self, time: Time) -> TimingState {
+        let mut state = TimingState::default();
+        if let Some(red) = self.uninherited_at(time) {
+            state.beat_length = f64::from(red.beat_length);
+            state.meter = red.meter;
+        }
+        if let Some(point) = self.point_at(time) {
+            state.slider_velocity = slider_velocity(point);
+            state.sample_set = point.sample_set;
+            state.sample_index = point.sample_index;
+            state.volume = point.volume;
+            state.kiai = point.effects.contains(Effects::KIAI);
+        }
+        state
+    }
+}
+
+/// The slider velocity multiplier of a timing point. Inherited points store it as a negative
+/// percentage in `beat_length`, which the client clamps to 0.1x-10x.
+pub fn slider_velocity(point: &TimingPoint) -> f64 {
+    if point.uninherited || point.beat_length.is_nan() || point.beat_length >= 0.0 {
+        return 1.0;
+    }
+    100.0 / f64::from(-point.beat_length).clamp(10.0, 1000.0)
+}
@@ -0,0 +1,140 @@
+use osu_types::{
+    BeatmapDifficulty, CurveType, Effects, HitObject, HitSample, HitSound, SpecificHitObject,
+    TimingPoint,
+};
+use osu_utils::{Slider, SliderEventKind, Spline, TimingIndex};
+
+fn slider(
+    curve_type: CurveType,
+    curve_points: &[(i16, i16)],
+    slides: u8,
+    length: f32,
+) -> HitObject {
+    HitObject {
+        position: mint::Point2 { x: 0, y: 0 },
+        time: 1000.0,
+        specific: SpecificHitObject::Slider {
+            curve_type,
+            curve_points: curve_points
+                .iter()
+                .map(|&(x, y)| mint::Point2 { x, y })
+                .collect(),
+            slides,
+            length,
+            edge_sounds: vec![HitSound::empty(); usize::from(slides) + 1],
+            edge_sets: Vec::new(),
+        },
+        hit_sound: HitSound::empty(),
+        hit_sample: HitSample::default(),
+        new_combo: true,
+        combo_skip: 0,
+    }
+}
+
+fn timing(slider_velocity: f32) -> TimingIndex {
+    let red = TimingPoint {
+        time: 0.0,
+        beat_length: 500.0,
+        meter: 4,
+        sample_set: None,
+        sample_index: 0,
+        volume: 100,
+        uninherited: true,
+        effects: Effects::empty(),
+    };
+    let green = TimingPoint {
+        beat_length: -100.0 / slider_velocity,
+        uninherited: false,
+        ..red
+    };
+    TimingIndex::new(&[red, green])
+}
+
+const DIFFICULTY: BeatmapDifficulty = BeatmapDifficulty {
+    hp: 5.0,
+    cs: 4.0,
+    od: 8.0,
+    ar: 9.0,
+    slider_multiplier: 1.0,
+    slider_tick_rate: 1.0,
+};
+
+#[test]
+fn test_slider_nested_objects() {
+    let object = slider(CurveType::Linear, &[(400, 0)], 2, 200.0);
+    let slider = Slider::new(&object, &DIFFICULTY, &timing(1.0)).unwrap();
+
+    // 100 px per beat of 500 ms, two spans of 200 px
+    assert_eq!(slider.span_duration, 1000.0);
+    assert_eq!(slider.end_time, 3000.0);
+    assert_eq!(
+        slider.spans().collect::<Vec<_>>(),
+        vec![(1000.0, 2000.0), (2000.0, 3000.0)]
+    );
+    assert_eq!(slider.spline.end_point(), mint::Point2 { x: 200.0, y: 0.0 });
+
+    let nested: Vec<_> = slider
+        .nested
+        .iter()
+        .map(|event| (event.kind, event.time, event.position.x))
+        .collect();
+    assert_eq!(
+        nested,
+        vec![
+            (SliderEventKind::Head, 1000.0, 0.0),
+            (SliderEventKind::Tick, 1500.0, 100.0),
+            (SliderEventKind::Repeat, 2000.0, 200.0),
+            (SliderEventKind::Tick, 2500.0, 100.0),
+            (SliderEventKind::Tail, 2964.0, 7.2),
+        ]
+    );
+    assert_eq!(
+        slider.position_at(2250.0),
+        mint::Point2 { x: 150.0, y: 0.0 }
+    );
+}
+
+#[test]
+fn test_slider_velocity() {
+    let object = slider(CurveType::Bezier, &[(50, 100), (100, 0)], 1, 100.0);
+    let slider = Slider::new(&object, &DIFFICULTY, &timing(2.0)).unwrap();
+    assert_eq!(slider.duration(), 250.0);
+    assert!((slider.spline.pixel_length() - 100.0).abs() < 0.01);
+
+    // Too short for a tick, the tail is judged 36 ms before the end.
+    let kinds: Vec<_> = slider.nested.iter().map(|event| event.kind).collect();
+    assert_eq!(kinds, vec![SliderEventKind::Head, SliderEventKind::Tail]);
+    assert_eq!(slider.nested[1].time, 1214.0);
+
+    let circle = HitObject {
+        specific: SpecificHitObject::Circle,
+        ..object
+    };
+    assert!(Slider::new(&circle, &DIFFICULTY, &timing(1.0)).is_none());
+}
+
+#[test]
+fn test_slider_degenerate_length() {
+    let timing = timing(1.0);
+
+    // A perfect curve with a length of 0 follows its whole arc, the spline itself stays on the
+    // head.
+    let object = slider(CurveType::Perfect, &[(50, 50), (100, 0)], 1, 0.0);
+    let slider_zero = Slider::new(&object, &DIFFICULTY, &timing).unwrap();
+    assert!((slider_zero.spline.end_point().x - 100.0).abs() < 0.01);
+    let control_points = [(0, 0), (50, 50), (100, 0)].map(|(x, y)| mint::Point2 { x, y });
+    let spline = Spline::from_control(CurveType::Perfect, &control_points, Some(0.0));
+    assert_eq!(spline.point_at_length(0.0), mint::Point2 { x: 0.0, y: 0.0 });
+
+    // Negative and NaN lengths fall back to the length of the path.
+    for length in [-100.0, f32::NAN] {
+        let object = slider(CurveType::Linear, &[(100, 0)], 1, length);
+        let slider = Slider::new(&object, &DIFFICULTY, &timing).unwrap();
+        assert_eq!(slider.spline.pixel_length(), 100.0);
+        assert_eq!(slider.end_time, 1500.0);
+        assert_eq!(
+            slider.position_at(slider.end_time),
+            mint::Point2 { x: 100.0, y: 0.0 }
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use osu_types::{
+    BeatmapDifficulty, CurveType, Effects, HitObject, HitSample, HitSound, SpecificHitObject,
+    TimingPoint,
+};
+use osu_utils::{stack_heights, stacked_position, StackingAlgorithm, TimingIndex};
+
+fn object(time: f64, x: i16, y: i16, specific: SpecificHitObject) -> HitObject {
+    HitObject {
+        position: mint::Point2 { x, y },
+        time,
+        specific,
+        hit_sound: HitSound::empty(),
+        hit_sample: HitSample::default(),
+        new_combo: false,
+        combo_skip: 0,
+    }
+}
+
+#[test]
+fn test_stack_heights() {
+    let slider = SpecificHitObject::Slider {
+        curve_type: CurveType::Linear,
+        curve_points: vec![mint::Point2 { x: 100, y: 0 }],
+        slides: 1,
+        length: 100.0,
+        edge_sounds: Vec::new(),
+        edge_sets: Vec::new(),
+    };
+    let objects = [
+        object(1000.0, 0, 0, slider),
+        // On the end of the slider
+        object(1600.0, 100, 0, SpecificHitObject::Circle),
+        object(2000.0, 300, 300, SpecificHitObject::Circle),
+        object(2100.0, 300, 300, SpecificHitObject::Circle),
+        object(2200.0, 301, 300, SpecificHitObject::Circle),
+        object(
+            3000.0,
+            256,
+            192,
+            SpecificHitObject::Spinner { end_time: 4000.0 },
+        ),
+        // Too late to stack on the previous circles
+        object(4500.0, 300, 300, SpecificHitObject::Circle),
+    ];
+    let difficulty = BeatmapDifficulty {
+        hp: 5.0,
+        cs: 4.0,
+        od: 8.0,
+        ar: 9.0,
+        slider_multiplier: 1.0,
+        slider_tick_rate: 1.0,
+    };
+    let timing = TimingIndex::new(&[TimingPoint {
+        time: 0.0,
+        beat_length: 500.0,
+        meter: 4,
+        sample_set: None,
+        sample_index: 0,
+        volume: 100,
+        uninherited: true,
+        effects: Effects::empty(),
+    }]);
+
+    for algorithm in [StackingAlgorithm::Legacy, StackingAlgorithm::Modern] {
+        let heights = stack_heights(&objects, &difficulty, &timing, 0.7, algorithm);
+        assert_eq!(heights, vec![0, -1, 2, 1, 0, 0, 0], "{:?}", algorithm);
+    }
+    assert_eq!(
+        StackingAlgorithm::for_format_version(5),
+        StackingAlgorithm::Legacy
+    );
+
+    let position = stacked_position(mint::Point2 { x: 300, y: 300 }, 2, difficulty.cs);
+    assert!((position.x - 292.704).abs() < 0.001);
+    assert!((position.y - 292.704).abs() < 0.001);
+}
+
+#[test]
+fn test_stack_heights_legacy_slider() {
+    let slider = SpecificHitObject::Slider {
+        curve_type: CurveType::Linear,
+        curve_points: vec![mint::Point2 { x: 456, y: 192 }],
+        slides: 1,
+        length: 500.0,
+        edge_sounds: Vec::new(),
+        edge_sets: Vec::new(),
+    };
+    let objects = [
+        object(1000.0, 256, 192, SpecificHitObject::Circle),
+        // Ends at 3500, after which the last circle would still stack on the first one.
+        object(1100.0, 256, 192, slider),
+        object(4000.0, 256, 192, SpecificHitObject::Circle),
+    ];
+    let difficulty = BeatmapDifficulty {
+        hp: 5.0,
+        cs: 4.0,
+        od: 5.0,
+        ar: 5.0,
+        slider_multiplier: 1.0,
+        slider_tick_rate: 1.0,
+    };
+    let timing = TimingIndex::new(&[TimingPoint {
+        time: 0.0,
+        beat_length: 500.0,
+        meter: 4,
+        sample_set: None,
+        sample_index: 0,
+        volume: 100,
+        uninherited: true,
+        effects: Effects::empty(),
+    }]);
+
+    // The stack window of the first circle moves on with the start of the slider, not its end.
+    let heights = stack_heights(
+        &objects,
+        &difficulty,
+        &timing,
+        0.7,
+        StackingAlgorithm::Legacy,
+    );
+    assert_eq!(heights, vec![1, 1, 0]);
+}
@@ -0,0 +1,58 @@
+use osu_types::{Effects, SampleSet, TimingPoint};
+use osu_utils::{TimingIndex, DEFAULT_BEAT_LENGTH};
+
+fn point(time: f64, beat_length: f32, uninherited: bool) -> TimingPoint {
+    TimingPoint {
+        time,
+        beat_length,
+        meter: 4,
+        sample_set: Some(SampleSet::Soft),
+        sample_index: 0,
+        volume: 100,
+        uninherited,
+        effects: Effects::empty(),
+    }
+}
+
+#[test]
+fn test_timing_lookup() {
+    let mut kiai = point(2000.0, -50.0, false);
+    kiai.effects = Effects::KIAI;
+    kiai.sample_index = 2;
+    kiai.volume = 60;
+    let mut waltz = point(3000.0, 300.0, true);
+    waltz.meter = 3;
+    // Out of order on purpose, and a green line at the same time as a red line.
+    let index = TimingIndex::new(&[
+        waltz,
+        point(1000.0, 500.0, true),
+        kiai,
+        point(3000.0, -200.0, false),
+        point(4000.0, -5.0, false),
+    ]);
+
+    assert_eq!(index.beat_length_at(0.0), 500.0);
+    assert_eq!(index.state_at(0.0).bpm(), 120.0);
+    assert_eq!(index.slider_velocity_at(1500.0), 1.0);
+    assert_eq!(index.slider_velocity_at(2000.0), 2.0);
+    assert!(index.kiai_at(2999.0));
+    assert!(!index.kiai_at(3000.0));
+
+    let state = index.state_at(2500.0);
+    assert_eq!(state.beat_length, 500.0);
+    assert_eq!(state.sample_index, 2);
+    assert_eq!(state.volume, 60);
+    assert_eq!(state.sample_set, Some(SampleSet::Soft));
+
+    let state = index.state_at(3000.0);
+    assert_eq!(state.beat_length, 300.0);
+    assert_eq!(state.meter, 3);
+    assert_eq!(state.slider_velocity, 0.5);
+    // Clamped to 10x.
+    assert_eq!(index.slider_velocity_at(5000.0), 10.0);
+
+    let empty = TimingIndex::new(&[]);
+    assert_eq!(empty.beat_length_at(0.0), DEFAULT_BEAT_LENGTH);
+    assert_eq!(empty.slider_velocity_at(0.0), 1.0);
+    assert!(empty.point_at(0.0).is_none());
+}